@@ -0,0 +1,270 @@
+use engine::Event;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub trait Clock {
+    fn now(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    started: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    time: Arc<Mutex<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn set(&self, time: Duration) {
+        *self.time.lock().unwrap() = time;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.time.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        *self.time.lock().unwrap()
+    }
+}
+
+pub struct FrameClock {
+    clock: Box<Clock>,
+    timestep: Duration,
+    frame_duration: Option<Duration>,
+    origin: Duration,
+    clock_origin: Duration,
+    last: Duration,
+    accumulator: Duration,
+    ticks: u64,
+    frames: u64,
+}
+
+impl FrameClock {
+    const MAX_TICKS_PER_FRAME: usize = 8;
+
+    pub fn new(clock: Box<Clock>, timestep: Duration) -> Result<FrameClock, String> {
+        if timestep == Duration::default() {
+            return Err("Timestep must be greater than zero".to_string());
+        }
+        let now = clock.now();
+        Ok(FrameClock {
+            clock,
+            timestep,
+            frame_duration: None,
+            origin: now,
+            clock_origin: now,
+            last: now,
+            accumulator: Duration::default(),
+            ticks: 0,
+            frames: 0,
+        })
+    }
+
+    pub fn now(&self) -> Duration {
+        self.last
+    }
+
+    pub fn set_frame_duration(&mut self, frame_duration: Option<Duration>) {
+        self.frame_duration = frame_duration;
+        self.origin = self.last;
+        self.clock_origin = self.clock.now();
+    }
+
+    fn clock_now(&self) -> Duration {
+        let elapsed = self
+            .clock
+            .now()
+            .checked_sub(self.clock_origin)
+            .unwrap_or_default();
+        self.origin + elapsed
+    }
+
+    pub fn skip(&mut self) {
+        if self.frame_duration.is_none() {
+            self.last = self.clock_now();
+        }
+    }
+
+    pub fn tick(&mut self) -> Vec<Event> {
+        let now = match self.frame_duration {
+            Some(frame_duration) => self.last + frame_duration,
+            None => self.clock_now(),
+        };
+        self.accumulator += now.checked_sub(self.last).unwrap_or_default();
+        self.last = now;
+
+        let mut out = vec![];
+        while self.accumulator >= self.timestep {
+            if out.len() == FrameClock::MAX_TICKS_PER_FRAME {
+                self.accumulator = Duration::default();
+                break;
+            }
+            self.accumulator -= self.timestep;
+            out.push(Event::Tick {
+                dt: self.timestep,
+                frame: self.ticks,
+            });
+            self.ticks += 1;
+        }
+        out
+    }
+
+    pub fn frame(&mut self) -> Event {
+        let out = Event::Frame {
+            frame: self.frames,
+            alpha: self.accumulator.as_secs_f32() / self.timestep.as_secs_f32(),
+            time: self.last,
+        };
+        self.frames += 1;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn frame_clock() -> (ManualClock, FrameClock) {
+        let clock = ManualClock::new();
+        let frame_clock =
+            FrameClock::new(Box::new(clock.clone()), Duration::from_millis(10)).unwrap();
+        (clock, frame_clock)
+    }
+
+    fn ticks(events: Vec<Event>) -> Vec<u64> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::Tick { dt, frame } => {
+                    assert_eq!(dt, Duration::from_millis(10));
+                    frame
+                }
+                _ => panic!("Expected tick"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ticks_on_fixed_timestep() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        clock.advance(Duration::from_millis(25));
+        assert_eq!(ticks(frame_clock.tick()), vec![0, 1]);
+
+        clock.advance(Duration::from_millis(4));
+        assert_eq!(ticks(frame_clock.tick()), vec![]);
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(ticks(frame_clock.tick()), vec![2]);
+    }
+
+    #[test]
+    fn test_frame_carries_interpolation_alpha_and_time() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        clock.advance(Duration::from_millis(25));
+        frame_clock.tick();
+        let first = frame_clock.frame();
+        let second = frame_clock.frame();
+
+        match (first, second) {
+            (
+                Event::Frame { frame, alpha, time },
+                Event::Frame {
+                    frame: next_frame, ..
+                },
+            ) => {
+                assert_eq!(frame, 0);
+                assert_eq!(next_frame, 1);
+                assert!((alpha - 0.5).abs() < 1e-6);
+                assert_eq!(time, Duration::from_millis(25));
+            }
+            _ => panic!("Expected frames"),
+        }
+    }
+
+    #[test]
+    fn test_ticks_per_frame_are_capped() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        clock.advance(Duration::from_secs(1));
+
+        assert_eq!(frame_clock.tick().len(), FrameClock::MAX_TICKS_PER_FRAME);
+        assert_eq!(frame_clock.tick().len(), 0);
+    }
+
+    #[test]
+    fn test_skip_discards_elapsed_time() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        clock.advance(Duration::from_millis(50));
+        frame_clock.skip();
+
+        assert_eq!(frame_clock.tick().len(), 0);
+    }
+
+    #[test]
+    fn test_ticks_resume_after_skip_without_catching_up() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        clock.advance(Duration::from_millis(15));
+        assert_eq!(ticks(frame_clock.tick()), vec![0]);
+        clock.advance(Duration::from_secs(60));
+        frame_clock.skip();
+        clock.advance(Duration::from_millis(5));
+
+        assert_eq!(ticks(frame_clock.tick()), vec![1]);
+        assert_eq!(frame_clock.now(), Duration::from_millis(60_020));
+    }
+
+    #[test]
+    fn test_zero_timestep_is_rejected() {
+        assert!(FrameClock::new(Box::new(ManualClock::new()), Duration::default()).is_err());
+    }
+
+    #[test]
+    fn test_fixed_frame_duration_ignores_clock() {
+        let (clock, mut frame_clock) = frame_clock();
+
+        frame_clock.set_frame_duration(Some(Duration::from_millis(20)));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(ticks(frame_clock.tick()), vec![0, 1]);
+        frame_clock.skip();
+        assert_eq!(ticks(frame_clock.tick()), vec![2, 3]);
+        assert_eq!(frame_clock.now(), Duration::from_millis(40));
+
+        frame_clock.set_frame_duration(None);
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(ticks(frame_clock.tick()), vec![4]);
+        assert_eq!(frame_clock.now(), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,119 @@
+use engine::{Command, Event};
+use events::EventHandler;
+use std::any::{Any, TypeId};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+pub struct CustomEvent {
+    type_name: &'static str,
+    payload: Box<Any + Send + Sync>,
+}
+
+impl CustomEvent {
+    pub fn new<T: Any + Send + Sync>(payload: T) -> CustomEvent {
+        CustomEvent {
+            type_name: std::any::type_name::<T>(),
+            payload: Box::new(payload),
+        }
+    }
+
+    pub fn type_id(&self) -> TypeId {
+        (*self.payload).type_id()
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn is<T: Any>(&self) -> bool {
+        self.payload.is::<T>()
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref::<T>()
+    }
+}
+
+impl Event {
+    pub fn custom<T: Any + Send + Sync>(payload: T) -> Event {
+        Event::Custom(CustomEvent::new(payload))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match self {
+            Event::Custom(custom) => custom.downcast_ref::<T>(),
+            _ => None,
+        }
+    }
+}
+
+pub type Subscription<T> = Box<FnMut(&T) -> Vec<Command> + Send>;
+
+pub struct Subscriber<T: Any> {
+    callback: Subscription<T>,
+    payload: PhantomData<fn(&T)>,
+}
+
+impl<T: Any> Subscriber<T> {
+    pub fn new(callback: Subscription<T>) -> Subscriber<T> {
+        Subscriber {
+            callback,
+            payload: PhantomData,
+        }
+    }
+}
+
+impl<T: Any> EventHandler for Subscriber<T> {
+    fn handle_event(&mut self, event: Arc<Event>) -> Vec<Command> {
+        match event.downcast_ref::<T>() {
+            Some(payload) => (self.callback)(payload),
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct UnitSelected(u32);
+
+    struct TurnEnded;
+
+    #[test]
+    fn test_downcast() {
+        let event = Event::custom(UnitSelected(7));
+
+        assert_eq!(event.downcast_ref::<UnitSelected>(), Some(&UnitSelected(7)));
+        assert!(event.downcast_ref::<TurnEnded>().is_none());
+        assert!(Event::WorldDrawn.downcast_ref::<UnitSelected>().is_none());
+        match event {
+            Event::Custom(custom) => {
+                assert!(custom.is::<UnitSelected>());
+                assert_eq!(custom.type_id(), TypeId::of::<UnitSelected>());
+                assert!(custom.type_name().ends_with("UnitSelected"));
+            }
+            _ => panic!("Expected custom event"),
+        }
+    }
+
+    #[test]
+    fn test_subscriber_only_receives_its_type() {
+        let mut subscriber = Subscriber::new(Box::new(|unit: &UnitSelected| {
+            vec![Command::Erase(format!("unit-{}", unit.0))]
+        }));
+
+        let selected = subscriber.handle_event(Arc::new(Event::custom(UnitSelected(3))));
+        let ended = subscriber.handle_event(Arc::new(Event::custom(TurnEnded)));
+        let drawn = subscriber.handle_event(Arc::new(Event::WorldDrawn));
+
+        match selected.as_slice() {
+            [Command::Erase(name)] => assert_eq!(name, "unit-3"),
+            _ => panic!("Expected erase command"),
+        }
+        assert!(ended.is_empty());
+        assert!(drawn.is_empty());
+    }
+}
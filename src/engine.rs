@@ -1,13 +1,63 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use clock::{Clock, FrameClock, SystemClock};
+use color::Color;
 use coords::*;
+use custom::CustomEvent;
+use error::Error;
 use event_handlers::*;
-use events::{AsyncEventHandler, EventHandler};
+use events::{AsyncEventHandler, EventHandler, EventHandlers, HandlerId};
 use graphics::drawing::*;
-use graphics::engine::GraphicsEngine;
+use graphics::engine::{DrawOrder, DrawingType, GraphicsEngine};
+use graphics::program::ProgramSetup;
+use graphics::recorder::{Recorder, RecordingOptions};
+use graphics::render_target::RenderPass;
+use graphics::shader_watcher::ShaderWakeThread;
+use image::RgbaImage;
+use query::{EngineView, QueryKind, QueryResult};
+use timer::{TimerThread, Timers};
+use transform::Isometric;
 
 use glutin::GlContext;
 
+const SWAP_INTERVAL_UNSUPPORTED: &str =
+    "Changing vsync at runtime is not supported for this context";
+
+#[cfg(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+unsafe fn extension_string(extensions: *const std::os::raw::c_char) -> String {
+    if extensions.is_null() {
+        return String::new();
+    }
+    std::ffi::CStr::from_ptr(extensions)
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd"
+))]
+fn has_extension(extensions: &str, name: &str) -> bool {
+    extensions
+        .split_whitespace()
+        .any(|extension| extension == name)
+}
+
 pub enum Event {
     Start,
     Shutdown,
@@ -18,6 +68,61 @@ pub enum Event {
     GlutinEvent(glutin::Event),
     Drag(GLCoord4D),
     WorldDrawn,
+    Screenshot {
+        path: PathBuf,
+        result: Result<(), String>,
+    },
+    ProgramError {
+        drawing_type: DrawingType,
+        error: Error,
+    },
+    DrawingError {
+        name: String,
+        error: String,
+    },
+    RecordingFrame {
+        frame: u64,
+        time: Duration,
+    },
+    RecordingStopped {
+        directory: PathBuf,
+        frames: u32,
+        result: Result<(), String>,
+    },
+    FullscreenChanged(bool),
+    TitleChanged(String),
+    VsyncChanged {
+        vsync: bool,
+        result: Result<(), String>,
+    },
+    CursorGrabChanged {
+        grabbed: bool,
+        result: Result<(), String>,
+    },
+    CursorVisibilityChanged(bool),
+    Tick {
+        dt: Duration,
+        frame: u64,
+    },
+    Frame {
+        frame: u64,
+        alpha: f32,
+        time: Duration,
+    },
+    TimerFired(String),
+    TimerError {
+        id: String,
+        error: String,
+    },
+    HandlerPanicked {
+        id: HandlerId,
+        message: String,
+    },
+    Custom(CustomEvent),
+    QueryResult {
+        reply_id: u64,
+        result: QueryResult,
+    },
     Key {
         key: glutin::VirtualKeyCode,
         state: glutin::ElementState,
@@ -45,10 +150,146 @@ pub enum Command {
     ComputeWorldPosition(GLCoord4D),
     Draw {
         name: String,
-        drawing: Box<Drawing + Send>,
+        build: DrawingBuilder,
+    },
+    DrawDescription {
+        name: String,
+        description: DrawingDescription,
+    },
+    DrawTerrain {
+        name: String,
+        description: TerrainDescription,
     },
     Erase(String),
     LookAt(WorldCoord),
+    Screenshot {
+        path: PathBuf,
+        include_ui: bool,
+    },
+    TiledScreenshot {
+        path: PathBuf,
+        tiles: u32,
+        include_ui: bool,
+    },
+    AddProgram {
+        drawing_type: DrawingType,
+        vertex_shader: String,
+        fragment_shader: String,
+        setup: ProgramSetup,
+    },
+    SetDrawOrder(DrawOrder),
+    WatchShaders(PathBuf),
+    AddRenderPass {
+        name: String,
+        pass: RenderPass,
+    },
+    RemoveRenderPass(String),
+    StartRecording(RecordingOptions),
+    StopRecording,
+    SetFullscreen(bool),
+    SetTitle(String),
+    SetWindowSize(glutin::dpi::LogicalSize),
+    SetVsync(bool),
+    GrabCursor(bool),
+    HideCursor(bool),
+    SetRenderMode(RenderMode),
+    RequestRedraw,
+    Schedule {
+        id: String,
+        delay: Duration,
+        command: Box<Command>,
+    },
+    StartTimer {
+        id: String,
+        interval: Duration,
+        repeat: bool,
+    },
+    CancelTimer(String),
+    AddHandler {
+        id: HandlerId,
+        handler: Box<EventHandler + Send>,
+        priority: i32,
+    },
+    RemoveHandler(HandlerId),
+    ReplaceHandler {
+        id: HandlerId,
+        handler: Box<EventHandler + Send>,
+    },
+    Query {
+        kind: QueryKind,
+        reply_id: u64,
+    },
+    QueryChannel {
+        kind: QueryKind,
+        reply: Sender<QueryResult>,
+    },
+}
+
+// The cursor and drag handlers act on WorldDrawn, so input needs a frame in on demand mode
+fn requires_redraw(event: &glutin::Event) -> bool {
+    matches!(
+        event,
+        glutin::Event::WindowEvent {
+            event: glutin::WindowEvent::Refresh
+                | glutin::WindowEvent::CursorMoved { .. }
+                | glutin::WindowEvent::MouseInput { .. },
+            ..
+        }
+    )
+}
+
+impl Command {
+    fn requires_redraw(&self) -> bool {
+        !matches!(
+            self,
+            Command::Shutdown
+                | Command::Event(..)
+                | Command::ComputeWorldPosition(..)
+                | Command::Screenshot { .. }
+                | Command::TiledScreenshot { .. }
+                | Command::WatchShaders(..)
+                | Command::StartRecording(..)
+                | Command::StopRecording
+                | Command::SetTitle(..)
+                | Command::SetVsync(..)
+                | Command::GrabCursor(..)
+                | Command::HideCursor(..)
+                | Command::Schedule { .. }
+                | Command::StartTimer { .. }
+                | Command::CancelTimer(..)
+                | Command::AddHandler { .. }
+                | Command::RemoveHandler(..)
+                | Command::ReplaceHandler { .. }
+                | Command::Query { .. }
+                | Command::QueryChannel { .. }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderMode {
+    Continuous,
+    // While idle the loop sleeps until an event arrives, so ticks pause and idle time is not replayed
+    OnDemand,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Activity {
+    continuous: bool,
+    redraw: bool,
+    recording: bool,
+    render_passes: bool,
+    pending_events: bool,
+}
+
+impl Activity {
+    fn needs_redraw(&self) -> bool {
+        self.continuous || self.redraw || self.recording || self.render_passes
+    }
+
+    fn is_idle(&self) -> bool {
+        !self.needs_redraw() && !self.pending_events
+    }
 }
 
 pub struct IsometricEngine {
@@ -56,104 +297,373 @@ pub struct IsometricEngine {
     window: glutin::GlWindow,
     graphics: GraphicsEngine,
     running: bool,
+    render_mode: RenderMode,
+    redraw: bool,
+    clock: FrameClock,
+    timers: Timers,
+    timer_thread: TimerThread,
+    shader_wake_thread: Option<ShaderWakeThread>,
     events: Vec<Event>,
-    event_handlers: Vec<Box<EventHandler>>,
+    event_handlers: EventHandlers,
+    default_handlers: HashMap<DefaultHandler, HandlerId>,
+    recorder: Option<Recorder>,
 }
 
-impl IsometricEngine {
-    const GL_VERSION: glutin::GlRequest = glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3));
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DefaultHandler {
+    Shutdown,
+    DPIRelay,
+    Resizer,
+    Cursor,
+    Drag,
+    ResizeRelay,
+    Scroller,
+    Zoom,
+    KeyRelay,
+}
+
+impl DefaultHandler {
+    pub const ALL: [DefaultHandler; 9] = [
+        DefaultHandler::Shutdown,
+        DefaultHandler::DPIRelay,
+        DefaultHandler::Resizer,
+        DefaultHandler::Cursor,
+        DefaultHandler::Drag,
+        DefaultHandler::ResizeRelay,
+        DefaultHandler::Scroller,
+        DefaultHandler::Zoom,
+        DefaultHandler::KeyRelay,
+    ];
 
-    pub fn new(title: &str, width: u32, height: u32, max_z: f32) -> IsometricEngine {
+    fn create(
+        self,
+        dpi_factor: f64,
+        logical_window_size: glutin::dpi::LogicalSize,
+    ) -> Box<EventHandler> {
+        match self {
+            DefaultHandler::Shutdown => {
+                Box::new(AsyncEventHandler::new(Box::new(ShutdownHandler::new())))
+            }
+            DefaultHandler::DPIRelay => Box::new(DPIRelay::new()),
+            DefaultHandler::Resizer => Box::new(Resizer::new()),
+            DefaultHandler::Cursor => Box::new(CursorHandler::new(dpi_factor, logical_window_size)),
+            DefaultHandler::Drag => Box::new(DragHandler::new()),
+            DefaultHandler::ResizeRelay => Box::new(ResizeRelay::new(dpi_factor)),
+            DefaultHandler::Scroller => Box::new(Scroller::new()),
+            DefaultHandler::Zoom => Box::new(ZoomHandler::new()),
+            DefaultHandler::KeyRelay => Box::new(KeyRelay::new()),
+        }
+    }
+}
+
+pub struct IsometricEngineBuilder {
+    title: String,
+    width: u32,
+    height: u32,
+    max_z: f32,
+    gl_version: (u8, u8),
+    vsync: bool,
+    multisampling: u16,
+    clear_color: Color,
+    yaw: f32,
+    pitch: f32,
+    render_mode: RenderMode,
+    timestep: Duration,
+    clock: Box<Clock>,
+    handlers: HashMap<DefaultHandler, Option<Box<EventHandler>>>,
+}
+
+impl IsometricEngineBuilder {
+    pub fn new(title: &str, width: u32, height: u32, max_z: f32) -> IsometricEngineBuilder {
+        IsometricEngineBuilder {
+            title: title.to_string(),
+            width,
+            height,
+            max_z,
+            gl_version: (3, 3),
+            vsync: true,
+            multisampling: 4,
+            clear_color: Color::new(0.0, 0.0, 1.0, 1.0),
+            yaw: PI / 4.0,
+            pitch: PI / 3.0,
+            render_mode: RenderMode::Continuous,
+            timestep: Duration::from_micros(16_667),
+            clock: Box::new(SystemClock::new()),
+            handlers: HashMap::new(),
+        }
+    }
+
+    pub fn with_gl_version(mut self, major: u8, minor: u8) -> IsometricEngineBuilder {
+        self.gl_version = (major, minor);
+        self
+    }
+
+    pub fn with_vsync(mut self, vsync: bool) -> IsometricEngineBuilder {
+        self.vsync = vsync;
+        self
+    }
+
+    pub fn with_multisampling(mut self, samples: u16) -> IsometricEngineBuilder {
+        self.multisampling = samples;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: Color) -> IsometricEngineBuilder {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn with_rotation(mut self, yaw: f32, pitch: f32) -> IsometricEngineBuilder {
+        self.yaw = yaw;
+        self.pitch = pitch;
+        self
+    }
+
+    pub fn with_render_mode(mut self, render_mode: RenderMode) -> IsometricEngineBuilder {
+        self.render_mode = render_mode;
+        self
+    }
+
+    pub fn with_timestep(mut self, timestep: Duration) -> IsometricEngineBuilder {
+        self.timestep = timestep;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Box<Clock>) -> IsometricEngineBuilder {
+        self.clock = clock;
+        self
+    }
+
+    pub fn without_handler(mut self, handler: DefaultHandler) -> IsometricEngineBuilder {
+        self.handlers.insert(handler, None);
+        self
+    }
+
+    pub fn with_handler(
+        mut self,
+        handler: DefaultHandler,
+        replacement: Box<EventHandler>,
+    ) -> IsometricEngineBuilder {
+        self.handlers.insert(handler, Some(replacement));
+        self
+    }
+
+    fn create_handlers<F>(&mut self, create: F) -> Vec<(DefaultHandler, Box<EventHandler>)>
+    where
+        F: Fn(DefaultHandler) -> Box<EventHandler>,
+    {
+        DefaultHandler::ALL
+            .iter()
+            .filter_map(|handler| match self.handlers.remove(handler) {
+                Some(replacement) => replacement.map(|replacement| (*handler, replacement)),
+                None => Some((*handler, create(*handler))),
+            })
+            .collect()
+    }
+
+    pub fn build(mut self) -> Result<IsometricEngine, Error> {
+        let clock = mem::replace(&mut self.clock, Box::new(SystemClock::new()));
+        let clock = FrameClock::new(clock, self.timestep).map_err(Error::Config)?;
         let events_loop = glutin::EventsLoop::new();
         let window = glutin::WindowBuilder::new()
-            .with_title(title)
-            .with_dimensions(glutin::dpi::LogicalSize::new(width as f64, height as f64));
+            .with_title(self.title.clone())
+            .with_dimensions(glutin::dpi::LogicalSize::new(
+                self.width as f64,
+                self.height as f64,
+            ));
         let context = glutin::ContextBuilder::new()
-            .with_gl(IsometricEngine::GL_VERSION)
-            .with_vsync(true)
-            .with_multisampling(4);
-        let gl_window = glutin::GlWindow::new(window, context, &events_loop).unwrap();
+            .with_gl(glutin::GlRequest::Specific(
+                glutin::Api::OpenGl,
+                self.gl_version,
+            ))
+            .with_vsync(self.vsync)
+            .with_multisampling(self.multisampling);
+        let gl_window = glutin::GlWindow::new(window, context, &events_loop)
+            .map_err(|err| Error::Window(err.to_string()))?;
 
         unsafe {
-            gl_window.make_current().unwrap();
+            gl_window
+                .make_current()
+                .map_err(|err| Error::Context(err.to_string()))?;
             gl::load_with(|symbol| gl_window.get_proc_address(symbol) as *const _);
         }
 
         let dpi_factor = gl_window.get_hidpi_factor();
-        let graphics = GraphicsEngine::new(
-            1.0 / max_z,
-            gl_window
-                .window()
-                .get_inner_size()
-                .unwrap()
-                .to_physical(dpi_factor),
-        );
+        let logical_window_size = gl_window
+            .window()
+            .get_inner_size()
+            .ok_or_else(|| Error::Window("Window no longer exists".to_string()))?;
+        let mut graphics = GraphicsEngine::new(
+            1.0 / self.max_z,
+            logical_window_size.to_physical(dpi_factor),
+        )?;
+        graphics.set_clear_color(self.clear_color);
+        graphics.set_projection(Isometric::new(self.yaw, self.pitch));
 
-        IsometricEngine {
+        let mut event_handlers = EventHandlers::new();
+        event_handlers.set_waker(events_loop.create_proxy());
+        let proxy = events_loop.create_proxy();
+        let timer_thread = TimerThread::new(Box::new(move || {
+            let _ = proxy.wakeup();
+        }));
+        let mut default_handlers = HashMap::new();
+        for (default_handler, handler) in
+            self.create_handlers(|handler| handler.create(dpi_factor, logical_window_size))
+        {
+            let id = event_handlers.add(handler, EventHandlers::DEFAULT_PRIORITY);
+            default_handlers.insert(default_handler, id);
+        }
+
+        Ok(IsometricEngine {
             events_loop,
-            event_handlers: IsometricEngine::init_event_handlers(&gl_window),
+            event_handlers,
+            default_handlers,
             window: gl_window,
             graphics,
             running: true,
+            render_mode: self.render_mode,
+            redraw: true,
+            clock,
+            timers: Timers::new(),
+            timer_thread,
+            shader_wake_thread: None,
             events: vec![Event::Start],
-        }
+            recorder: None,
+        })
+    }
+}
+
+impl IsometricEngine {
+    pub fn new(title: &str, width: u32, height: u32, max_z: f32) -> Result<IsometricEngine, Error> {
+        IsometricEngineBuilder::new(title, width, height, max_z).build()
+    }
+
+    pub fn add_event_handler(&mut self, event_handler: Box<EventHandler>) -> HandlerId {
+        self.add_event_handler_with_priority(event_handler, EventHandlers::DEFAULT_PRIORITY)
     }
 
-    pub fn add_event_handler(&mut self, event_handler: Box<EventHandler>) {
-        self.event_handlers.push(event_handler);
+    pub fn add_event_handler_with_priority(
+        &mut self,
+        event_handler: Box<EventHandler>,
+        priority: i32,
+    ) -> HandlerId {
+        self.event_handlers.add(event_handler, priority)
     }
 
-    fn init_event_handlers(window: &glutin::GlWindow) -> Vec<Box<EventHandler>> {
-        let dpi_factor = window.get_hidpi_factor();
-        let logical_window_size = window.window().get_inner_size().unwrap();
+    pub fn get_default_handler_id(&self, default_handler: DefaultHandler) -> Option<HandlerId> {
+        self.default_handlers.get(&default_handler).cloned()
+    }
 
-        vec![
-            Box::new(AsyncEventHandler::new(Box::new(ShutdownHandler::new()))),
-            Box::new(DPIRelay::new()),
-            Box::new(Resizer::new()),
-            Box::new(CursorHandler::new(dpi_factor, logical_window_size)),
-            Box::new(DragHandler::new()),
-            Box::new(ResizeRelay::new(dpi_factor)),
-            Box::new(Scroller::new()),
-            Box::new(ZoomHandler::new()),
-            Box::new(KeyRelay::new()),
-        ]
+    pub fn create_proxy(&self) -> glutin::EventsLoopProxy {
+        self.events_loop.create_proxy()
     }
 
     pub fn run(&mut self) {
         while self.running {
+            if self.is_idle() {
+                self.wake_for_next_timer();
+                self.wait_for_glutin_events();
+                self.clock.skip();
+            }
             self.add_glutin_events();
             let mut to_process = vec![];
             to_process.append(&mut self.events);
             self.handle_events(to_process);
+            let ticks = self.clock.tick();
+            self.handle_events(ticks);
+            self.fire_timers();
+            self.reload_shaders();
+            if !self.running || !self.needs_redraw() {
+                continue;
+            }
+            self.redraw = false;
+            let frame = self.clock.frame();
+            self.handle_events(vec![frame]);
+            self.graphics.draw_render_passes();
             self.graphics.update_transform_matrix();
+            self.graphics.clear();
             self.graphics.draw_world();
             self.handle_events(vec![Event::WorldDrawn]);
-            self.graphics.draw_billboards();
             self.graphics.draw_ui();
+            self.record_frame();
             self.window.swap_buffers().unwrap();
         }
 
         self.shutdown();
     }
 
+    fn activity(&self) -> Activity {
+        Activity {
+            continuous: self.render_mode == RenderMode::Continuous,
+            redraw: self.redraw,
+            recording: self.recorder.is_some(),
+            render_passes: self.graphics.has_due_render_passes(),
+            pending_events: !self.events.is_empty(),
+        }
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.activity().needs_redraw()
+    }
+
+    fn is_idle(&self) -> bool {
+        self.running && self.activity().is_idle()
+    }
+
+    fn fire_timers(&mut self) {
+        for command in self.timers.due(self.clock.now()) {
+            self.handle_command(command);
+        }
+    }
+
+    fn wake_for_next_timer(&self) {
+        let now = self.clock.now();
+        self.timer_thread.wake_at(
+            self.timers
+                .next_due()
+                .map(|due| Instant::now() + due.checked_sub(now).unwrap_or_default()),
+        );
+    }
+
+    fn add_glutin_event(&mut self, event: glutin::Event) {
+        if requires_redraw(&event) {
+            self.redraw = true;
+        }
+        self.events.push(Event::GlutinEvent(event));
+    }
+
+    fn wait_for_glutin_events(&mut self) {
+        let mut glutin_events = vec![];
+        self.events_loop.run_forever(|event| {
+            glutin_events.push(event);
+            glutin::ControlFlow::Break
+        });
+        glutin_events
+            .into_iter()
+            .for_each(|event| self.add_glutin_event(event));
+    }
+
     fn add_glutin_events(&mut self) {
         let mut glutin_events = vec![];
         self.events_loop.poll_events(|event| {
-            glutin_events.push(Event::GlutinEvent(event));
+            glutin_events.push(event);
         });
-        self.events.append(&mut glutin_events);
+        glutin_events
+            .into_iter()
+            .for_each(|event| self.add_glutin_event(event));
     }
 
     fn handle_events(&mut self, events: Vec<Event>) {
         let mut commands = vec![];
 
-        events.into_iter().for_each(|event| {
-            let event_arc = Arc::new(event);
-            for handler in self.event_handlers.iter_mut() {
-                commands.append(&mut handler.handle_event(event_arc.clone()));
-            }
-        });
+        for event in events {
+            let view = EngineView::new(&self.graphics);
+            commands.append(
+                &mut self
+                    .event_handlers
+                    .handle_event_with_view(Arc::new(event), &view),
+            );
+        }
 
         for command in commands {
             self.handle_command(command);
@@ -161,6 +671,9 @@ impl IsometricEngine {
     }
 
     fn handle_command(&mut self, command: Command) {
+        if command.requires_redraw() {
+            self.redraw = true;
+        }
         match command {
             Command::Shutdown => self.running = false,
             Command::Resize(physical_size) => {
@@ -176,15 +689,470 @@ impl IsometricEngine {
                     gl_coord.to_world_coord(&self.graphics.get_transform()),
                 ))
             }
-            Command::Draw { name, drawing } => self.graphics.add_drawing(name, drawing),
+            Command::Draw { name, build } => self.graphics.add_drawing(name, build()),
+            Command::DrawDescription { name, description } => {
+                self.graphics.add_description(name, description)
+            }
+            Command::DrawTerrain { name, description } => {
+                if let Err(err) = self.graphics.add_terrain(name.clone(), description) {
+                    self.events.push(Event::DrawingError {
+                        name,
+                        error: format!("{:?}", err),
+                    });
+                }
+            }
             Command::Erase(name) => self.graphics.remove_drawing(&name),
             Command::LookAt(world_coord) => self.graphics.get_transform().look_at(world_coord),
+            Command::Screenshot { path, include_ui } => {
+                let image = self.graphics.capture(include_ui);
+                self.save_screenshot(image, path);
+            }
+            Command::TiledScreenshot {
+                path,
+                tiles,
+                include_ui,
+            } => match self.graphics.capture_tiled(tiles, include_ui) {
+                Ok(image) => self.save_screenshot(image, path),
+                Err(err) => self.events.push(Event::Screenshot {
+                    path,
+                    result: Err(err),
+                }),
+            },
+            Command::AddProgram {
+                drawing_type,
+                vertex_shader,
+                fragment_shader,
+                setup,
+            } => {
+                if let Err(error) =
+                    self.graphics
+                        .add_program(drawing_type, &vertex_shader, &fragment_shader, setup)
+                {
+                    self.events.push(Event::ProgramError {
+                        drawing_type,
+                        error,
+                    });
+                }
+            }
+            Command::SetDrawOrder(draw_order) => self.graphics.set_draw_order(draw_order),
+            Command::WatchShaders(directory) => {
+                let proxy = self.events_loop.create_proxy();
+                self.shader_wake_thread = Some(ShaderWakeThread::new(
+                    directory.clone(),
+                    Box::new(move || {
+                        let _ = proxy.wakeup();
+                    }),
+                ));
+                self.graphics.watch_shaders(directory);
+            }
+            Command::AddRenderPass { name, pass } => {
+                if let Err(error) = self.graphics.add_render_pass(name.clone(), pass) {
+                    self.events.push(Event::DrawingError { name, error });
+                }
+            }
+            Command::RemoveRenderPass(name) => self.graphics.remove_render_pass(&name),
+            Command::StartRecording(options) => self.start_recording(options),
+            Command::StopRecording => self.stop_recording(),
+            Command::SetFullscreen(fullscreen) => self.set_fullscreen(fullscreen),
+            Command::SetTitle(title) => {
+                self.window.set_title(&title);
+                self.events.push(Event::TitleChanged(title));
+            }
+            Command::SetWindowSize(logical_size) => {
+                self.window.set_inner_size(logical_size);
+            }
+            Command::SetVsync(vsync) => {
+                let result = self.set_swap_interval(if vsync { 1 } else { 0 });
+                self.events.push(Event::VsyncChanged { vsync, result });
+            }
+            Command::GrabCursor(grabbed) => {
+                let result = self.window.grab_cursor(grabbed);
+                self.events
+                    .push(Event::CursorGrabChanged { grabbed, result });
+            }
+            Command::HideCursor(hidden) => {
+                self.window.hide_cursor(hidden);
+                self.events.push(Event::CursorVisibilityChanged(!hidden));
+            }
+            Command::SetRenderMode(render_mode) => self.render_mode = render_mode,
+            Command::RequestRedraw => (),
+            Command::Schedule { id, delay, command } => {
+                self.timers.schedule(self.clock.now(), id, delay, *command)
+            }
+            Command::StartTimer {
+                id,
+                interval,
+                repeat,
+            } => {
+                if let Err(error) =
+                    self.timers
+                        .start(self.clock.now(), id.clone(), interval, repeat)
+                {
+                    self.events.push(Event::TimerError { id, error });
+                }
+            }
+            Command::CancelTimer(id) => self.timers.cancel(&id),
+            Command::AddHandler {
+                id,
+                handler,
+                priority,
+            } => self.event_handlers.insert(id, handler, priority),
+            Command::RemoveHandler(id) => {
+                self.event_handlers.remove(id);
+            }
+            Command::ReplaceHandler { id, handler } => {
+                self.event_handlers.replace(id, handler);
+            }
+            Command::Query { kind, reply_id } => {
+                let result = EngineView::new(&self.graphics).query(&kind);
+                self.events.push(Event::QueryResult { reply_id, result });
+            }
+            Command::QueryChannel { kind, reply } => {
+                let _ = reply.send(EngineView::new(&self.graphics).query(&kind));
+            }
+        }
+    }
+
+    fn set_fullscreen(&mut self, fullscreen: bool) {
+        let monitor = if fullscreen {
+            Some(self.window.get_current_monitor())
+        } else {
+            None
+        };
+        self.window.set_fullscreen(monitor);
+        self.events.push(Event::FullscreenChanged(fullscreen));
+    }
+
+    #[cfg(target_os = "windows")]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), String> {
+        use glutin::os::windows::RawHandle;
+        use glutin::os::GlContextExt;
+        use std::os::raw::c_char;
+        if let RawHandle::Egl(_) = unsafe { self.window.context().raw_handle() } {
+            return Err(SWAP_INTERVAL_UNSUPPORTED.to_string());
+        }
+        let get_extensions = self.proc_address("wglGetExtensionsStringEXT")?;
+        let extensions = unsafe {
+            let get_extensions: extern "system" fn() -> *const c_char =
+                std::mem::transmute(get_extensions);
+            extension_string(get_extensions())
+        };
+        if !has_extension(&extensions, "WGL_EXT_swap_control") {
+            return Err(SWAP_INTERVAL_UNSUPPORTED.to_string());
+        }
+        let swap_interval = self.proc_address("wglSwapIntervalEXT")?;
+        let result = unsafe {
+            let swap_interval: extern "system" fn(i32) -> i32 = std::mem::transmute(swap_interval);
+            swap_interval(interval)
+        };
+        match result {
+            0 => Err("wglSwapIntervalEXT failed".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd"
+    ))]
+    fn set_swap_interval(&self, interval: i32) -> Result<(), String> {
+        use glutin::os::unix::{RawHandle, WindowExt};
+        use glutin::os::GlContextExt;
+        use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+        if let RawHandle::Egl(_) = unsafe { self.window.context().raw_handle() } {
+            return Err(SWAP_INTERVAL_UNSUPPORTED.to_string());
+        }
+        let unsupported = || SWAP_INTERVAL_UNSUPPORTED.to_string();
+        let display = self.window.get_xlib_display().ok_or_else(unsupported)?;
+        let screen = self.window.get_xlib_screen_id().ok_or_else(unsupported)?;
+        let drawable = self.window.get_xlib_window().ok_or_else(unsupported)?;
+        let query_extensions = self.proc_address("glXQueryExtensionsString")?;
+        let extensions = unsafe {
+            let query_extensions: extern "C" fn(*mut c_void, c_int) -> *const c_char =
+                std::mem::transmute(query_extensions);
+            extension_string(query_extensions(display, screen))
+        };
+        if has_extension(&extensions, "GLX_MESA_swap_control") {
+            let swap_interval = self.proc_address("glXSwapIntervalMESA")?;
+            let result = unsafe {
+                let swap_interval: extern "C" fn(c_uint) -> c_int =
+                    std::mem::transmute(swap_interval);
+                swap_interval(interval as c_uint)
+            };
+            match result {
+                0 => Ok(()),
+                error => Err(format!("glXSwapIntervalMESA failed with {}", error)),
+            }
+        } else if has_extension(&extensions, "GLX_EXT_swap_control") {
+            let swap_interval = self.proc_address("glXSwapIntervalEXT")?;
+            unsafe {
+                let swap_interval: extern "C" fn(*mut c_void, c_ulong, c_int) =
+                    std::mem::transmute(swap_interval);
+                swap_interval(display, drawable, interval);
+            }
+            Ok(())
+        } else {
+            Err(unsupported())
+        }
+    }
+
+    #[cfg(not(any(
+        target_os = "windows",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd"
+    )))]
+    fn set_swap_interval(&self, _: i32) -> Result<(), String> {
+        Err(SWAP_INTERVAL_UNSUPPORTED.to_string())
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd"
+    ))]
+    fn proc_address(&self, name: &str) -> Result<*const (), String> {
+        let function = self.window.get_proc_address(name);
+        if function.is_null() {
+            return Err(format!("{} is not available", name));
+        }
+        Ok(function)
+    }
+
+    fn reload_shaders(&mut self) {
+        for (drawing_type, result) in self.graphics.reload_shaders() {
+            match result {
+                Ok(()) => self.redraw = true,
+                Err(error) => self.events.push(Event::ProgramError {
+                    drawing_type,
+                    error,
+                }),
+            }
+        }
+    }
+
+    fn save_screenshot(&mut self, image: RgbaImage, path: PathBuf) {
+        let result = image.save(&path).map_err(|err| err.to_string());
+        self.events.push(Event::Screenshot { path, result });
+    }
+
+    fn start_recording(&mut self, options: RecordingOptions) {
+        self.stop_recording();
+        let directory = options.directory.clone();
+        let frame_duration = options.frame_duration();
+        match Recorder::start(options) {
+            Ok(recorder) => {
+                self.clock.set_frame_duration(frame_duration);
+                self.recorder = Some(recorder);
+            }
+            Err(err) => self.events.push(Event::RecordingStopped {
+                directory,
+                frames: 0,
+                result: Err(err),
+            }),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            self.clock.set_frame_duration(None);
+            let directory = recorder.directory().clone();
+            let (frames, result) = recorder.stop();
+            self.events.push(Event::RecordingStopped {
+                directory,
+                frames,
+                result,
+            });
+        }
+    }
+
+    fn record_frame(&mut self) {
+        if let Some(recorder) = &mut self.recorder {
+            let viewport_size = self.graphics.get_viewport_size();
+            let (frame, time) =
+                recorder.capture(viewport_size.width as u32, viewport_size.height as u32);
+            self.events.push(Event::RecordingFrame { frame, time });
         }
     }
 
     fn shutdown(&mut self) {
-        for handler in &mut self.event_handlers {
-            handler.handle_event(Arc::new(Event::Shutdown));
+        self.stop_recording();
+        self.event_handlers.handle_event(Arc::new(Event::Shutdown));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use graphics::device::RecordingDevice;
+    use std::sync::Mutex;
+
+    struct Counter {
+        events: Arc<Mutex<u32>>,
+    }
+
+    impl EventHandler for Counter {
+        fn handle_event(&mut self, _: Arc<Event>) -> Vec<Command> {
+            *self.events.lock().unwrap() += 1;
+            vec![]
         }
     }
+
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn test_has_extension_matches_whole_names() {
+        let extensions = "GLX_EXT_swap_control_tear GLX_MESA_swap_control";
+
+        assert!(has_extension(extensions, "GLX_MESA_swap_control"));
+        assert!(!has_extension(extensions, "GLX_EXT_swap_control"));
+        assert!(!has_extension("", "GLX_MESA_swap_control"));
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "openbsd"
+    ))]
+    #[test]
+    fn test_null_extension_string_is_empty() {
+        assert_eq!(unsafe { extension_string(std::ptr::null()) }, "");
+    }
+
+    fn cursor_moved(x: f64, y: f64) -> glutin::Event {
+        // The dummy ids are never passed back into glutin
+        unsafe {
+            glutin::Event::WindowEvent {
+                window_id: glutin::WindowId::dummy(),
+                event: glutin::WindowEvent::CursorMoved {
+                    device_id: glutin::DeviceId::dummy(),
+                    position: glutin::dpi::LogicalPosition::new(x, y),
+                    modifiers: glutin::ModifiersState::default(),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_cursor_move_on_demand_draws_a_frame_and_reports_world_position() {
+        RecordingDevice::install();
+        let event = cursor_moved(8.0, 8.0);
+        let activity = Activity {
+            redraw: requires_redraw(&event),
+            ..Activity::default()
+        };
+        let mut handlers = EventHandlers::new();
+        handlers.add(
+            Box::new(CursorHandler::new(
+                1.0,
+                glutin::dpi::LogicalSize::new(16.0, 16.0),
+            )),
+            EventHandlers::DEFAULT_PRIORITY,
+        );
+
+        assert!(activity.needs_redraw());
+        assert!(handlers
+            .handle_event(Arc::new(Event::GlutinEvent(event)))
+            .is_empty());
+        let commands = handlers.handle_event(Arc::new(Event::WorldDrawn));
+
+        assert!(commands
+            .iter()
+            .any(|command| matches!(command, Command::ComputeWorldPosition(..))));
+    }
+
+    #[test]
+    fn test_activity_decides_idle_and_redraw() {
+        let idle = Activity::default();
+        assert!(idle.is_idle());
+        assert!(!idle.needs_redraw());
+
+        let pending = Activity {
+            pending_events: true,
+            ..Activity::default()
+        };
+        assert!(!pending.is_idle());
+        assert!(!pending.needs_redraw());
+
+        for activity in &[
+            Activity {
+                continuous: true,
+                ..Activity::default()
+            },
+            Activity {
+                redraw: true,
+                ..Activity::default()
+            },
+            Activity {
+                recording: true,
+                ..Activity::default()
+            },
+            Activity {
+                render_passes: true,
+                ..Activity::default()
+            },
+        ] {
+            assert!(activity.needs_redraw());
+            assert!(!activity.is_idle());
+        }
+    }
+
+    #[test]
+    fn test_zero_timestep_is_rejected_before_creating_window() {
+        let result = IsometricEngineBuilder::new("test", 64, 64, 100.0)
+            .with_timestep(Duration::default())
+            .build();
+
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_default_handlers_can_be_removed_or_replaced() {
+        let events = Arc::new(Mutex::new(0));
+        let mut builder = IsometricEngineBuilder::new("test", 64, 64, 100.0)
+            .without_handler(DefaultHandler::Zoom)
+            .without_handler(DefaultHandler::Shutdown)
+            .with_handler(
+                DefaultHandler::KeyRelay,
+                Box::new(Counter {
+                    events: events.clone(),
+                }),
+            );
+
+        let mut handlers = builder.create_handlers(|_| Box::new(KeyRelay::new()));
+        for (_, handler) in handlers.iter_mut() {
+            handler.handle_event(Arc::new(Event::Start));
+        }
+
+        assert_eq!(handlers.len(), DefaultHandler::ALL.len() - 2);
+        assert!(handlers
+            .iter()
+            .all(|(handler, _)| *handler != DefaultHandler::Zoom));
+        assert_eq!(*events.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_requires_redraw() {
+        assert!(Command::RequestRedraw.requires_redraw());
+        assert!(Command::Erase("drawing".to_string()).requires_redraw());
+        assert!(Command::Translate(GLCoord2D::new(1.0, 0.0)).requires_redraw());
+        assert!(!Command::Event(Event::WorldDrawn).requires_redraw());
+        assert!(
+            !Command::ComputeWorldPosition(GLCoord4D::new(0.0, 0.0, 0.0, 1.0)).requires_redraw()
+        );
+        assert!(!Command::SetTitle("title".to_string()).requires_redraw());
+    }
 }
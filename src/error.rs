@@ -0,0 +1,25 @@
+use std::error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    Window(String),
+    Context(String),
+    Shader(String),
+    Font(String),
+    Config(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Window(message) => write!(f, "Could not create window: {}", message),
+            Error::Context(message) => write!(f, "Could not create GL context: {}", message),
+            Error::Shader(message) => write!(f, "Could not build shader program: {}", message),
+            Error::Font(message) => write!(f, "Could not load font: {}", message),
+            Error::Config(message) => write!(f, "Invalid engine configuration: {}", message),
+        }
+    }
+}
+
+impl error::Error for Error {}
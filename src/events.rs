@@ -1,80 +1,851 @@
 use engine::{Command, Event};
+use query::EngineView;
 
+use std::any::Any;
+use std::collections::VecDeque;
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc;
-use std::sync::mpsc::{Receiver, RecvError, Sender, TryRecvError};
-use std::sync::Arc;
-use std::thread;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub struct HandlerResult {
+    pub commands: Vec<Command>,
+    pub consume: bool,
+}
 
 pub trait EventHandler {
     fn handle_event(&mut self, event: Arc<Event>) -> Vec<Command>;
+
+    fn handle_event_with_view(&mut self, event: Arc<Event>, _: &EngineView) -> Vec<Command> {
+        self.handle_event(event)
+    }
+
+    // Override to stop an event reaching handlers with lower priority
+    fn handle_event_with_result(
+        &mut self,
+        event: Arc<Event>,
+        view: Option<&EngineView>,
+    ) -> HandlerResult {
+        let commands = match view {
+            Some(view) => self.handle_event_with_view(event, view),
+            None => self.handle_event(event),
+        };
+        HandlerResult {
+            commands,
+            consume: false,
+        }
+    }
+
+    fn set_waker(&mut self, _: glutin::EventsLoopProxy) {}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HandlerId(u64);
+
+impl HandlerId {
+    pub fn next() -> HandlerId {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        HandlerId(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+struct PrioritizedHandler {
+    id: HandlerId,
+    priority: i32,
+    handler: Box<EventHandler>,
+}
+
+#[derive(Default)]
+pub struct EventHandlers {
+    handlers: Vec<PrioritizedHandler>,
+    waker: Option<glutin::EventsLoopProxy>,
+}
+
+impl EventHandlers {
+    pub const DEFAULT_PRIORITY: i32 = 0;
+
+    pub fn new() -> EventHandlers {
+        EventHandlers::default()
+    }
+
+    pub fn set_waker(&mut self, waker: glutin::EventsLoopProxy) {
+        for prioritized in self.handlers.iter_mut() {
+            prioritized.handler.set_waker(waker.clone());
+        }
+        self.waker = Some(waker);
+    }
+
+    fn wake(&self, handler: &mut Box<EventHandler>) {
+        if let Some(waker) = &self.waker {
+            handler.set_waker(waker.clone());
+        }
+    }
+
+    pub fn add(&mut self, handler: Box<EventHandler>, priority: i32) -> HandlerId {
+        let id = HandlerId::next();
+        self.insert(id, handler, priority);
+        id
+    }
+
+    pub fn insert(&mut self, id: HandlerId, mut handler: Box<EventHandler>, priority: i32) {
+        self.remove(id);
+        self.wake(&mut handler);
+        let index = self
+            .handlers
+            .iter()
+            .position(|existing| existing.priority < priority)
+            .unwrap_or(self.handlers.len());
+        self.handlers.insert(
+            index,
+            PrioritizedHandler {
+                id,
+                priority,
+                handler,
+            },
+        );
+    }
+
+    pub fn remove(&mut self, id: HandlerId) -> Option<Box<EventHandler>> {
+        let index = self
+            .handlers
+            .iter()
+            .position(|existing| existing.id == id)?;
+        Some(self.handlers.remove(index).handler)
+    }
+
+    pub fn replace(
+        &mut self,
+        id: HandlerId,
+        mut handler: Box<EventHandler>,
+    ) -> Option<Box<EventHandler>> {
+        self.wake(&mut handler);
+        let existing = self
+            .handlers
+            .iter_mut()
+            .find(|existing| existing.id == id)?;
+        Some(mem::replace(&mut existing.handler, handler))
+    }
+
+    pub fn handle_event(&mut self, event: Arc<Event>) -> Vec<Command> {
+        self.dispatch(event, None)
+    }
+
+    pub fn handle_event_with_view(&mut self, event: Arc<Event>, view: &EngineView) -> Vec<Command> {
+        self.dispatch(event, Some(view))
+    }
+
+    fn dispatch(&mut self, event: Arc<Event>, view: Option<&EngineView>) -> Vec<Command> {
+        let consumable = !matches!(*event, Event::Shutdown);
+        let mut out = vec![];
+        let mut panicked = vec![];
+        for prioritized in self.handlers.iter_mut() {
+            let handler = &mut prioritized.handler;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                handler.handle_event_with_result(event.clone(), view)
+            }));
+            match result {
+                Ok(mut result) => {
+                    out.append(&mut result.commands);
+                    if result.consume && consumable {
+                        break;
+                    }
+                }
+                Err(payload) => {
+                    out.push(Command::Event(Event::HandlerPanicked {
+                        id: prioritized.id,
+                        message: panic_message(payload),
+                    }));
+                    panicked.push(prioritized.id);
+                }
+            }
+        }
+        self.handlers
+            .retain(|prioritized| !panicked.contains(&prioritized.id));
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OverflowPolicy {
+    // Blocks the render thread inside send_event until the worker takes an event
+    Block,
+    DropNewest,
+    DropOldest,
+}
+
+pub type Coalesce = Box<Fn(&Event) -> bool>;
+
+pub struct AsyncOptions {
+    pub capacity: Option<usize>,
+    pub overflow_policy: OverflowPolicy,
+    pub shutdown_timeout: Duration,
+    pub proxy: Option<glutin::EventsLoopProxy>,
+    pub coalesce: Option<Coalesce>,
+}
+
+impl Default for AsyncOptions {
+    fn default() -> AsyncOptions {
+        AsyncOptions {
+            capacity: None,
+            overflow_policy: OverflowPolicy::Block,
+            shutdown_timeout: Duration::from_secs(1),
+            proxy: None,
+            coalesce: None,
+        }
+    }
+}
+
+impl AsyncOptions {
+    pub fn new() -> AsyncOptions {
+        AsyncOptions::default()
+    }
+
+    pub fn with_capacity(
+        mut self,
+        capacity: usize,
+        overflow_policy: OverflowPolicy,
+    ) -> AsyncOptions {
+        self.capacity = Some(capacity.max(1));
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> AsyncOptions {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: glutin::EventsLoopProxy) -> AsyncOptions {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    pub fn with_coalescing(mut self, coalesce: Coalesce) -> AsyncOptions {
+        self.coalesce = Some(coalesce);
+        self
+    }
+
+    pub fn coalesce_motion(event: &Event) -> bool {
+        matches!(
+            event,
+            Event::CursorMoved(..) | Event::WorldPositionChanged(..)
+        )
+    }
+}
+
+#[derive(Default)]
+struct EventQueue {
+    events: VecDeque<Arc<Event>>,
+    shutting_down: bool,
+    closed: bool,
+    dropped: u64,
 }
 
+type SharedQueue = Arc<(Mutex<EventQueue>, Condvar)>;
+type SharedProxy = Arc<Mutex<Option<glutin::EventsLoopProxy>>>;
+
 pub struct AsyncEventHandler {
-    event_tx: Sender<Arc<Event>>,
-    command_rx: Receiver<Vec<Command>>,
+    queue: SharedQueue,
+    command_rx: Receiver<Result<Vec<Command>, String>>,
+    done_rx: Receiver<()>,
+    thread: Option<JoinHandle<()>>,
+    panic: Option<String>,
+    proxy: SharedProxy,
+    capacity: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    shutdown_timeout: Duration,
+    coalesce: Option<Coalesce>,
 }
 
 impl AsyncEventHandler {
-    pub fn new(mut event_handler: Box<EventHandler + Send>) -> AsyncEventHandler {
-        let (event_tx, event_rx) = mpsc::channel();
+    pub fn new(event_handler: Box<EventHandler + Send>) -> AsyncEventHandler {
+        AsyncEventHandler::with_options(event_handler, AsyncOptions::new())
+    }
+
+    pub fn with_proxy(
+        event_handler: Box<EventHandler + Send>,
+        proxy: glutin::EventsLoopProxy,
+    ) -> AsyncEventHandler {
+        AsyncEventHandler::with_options(event_handler, AsyncOptions::new().with_proxy(proxy))
+    }
+
+    pub fn with_options(
+        event_handler: Box<EventHandler + Send>,
+        options: AsyncOptions,
+    ) -> AsyncEventHandler {
+        let queue: SharedQueue = Arc::new((Mutex::new(EventQueue::default()), Condvar::new()));
         let (command_tx, command_rx) = mpsc::channel();
+        let (done_tx, done_rx) = mpsc::channel();
+        let worker_queue = queue.clone();
+        let proxy: SharedProxy = Arc::new(Mutex::new(options.proxy));
+        let worker_proxy = proxy.clone();
 
-        thread::spawn(move || {
-            let send_commands = |commands: Vec<Command>| match command_tx.send(commands) {
-                Ok(_) => true,
-                _ => panic!("Command receiver in AsyncEventHandler hung up!"),
-            };
+        let thread = thread::spawn(move || {
+            AsyncEventHandler::work(event_handler, &worker_queue, &command_tx, &worker_proxy);
+            let (lock, condvar) = &*worker_queue;
+            lock.lock().unwrap().closed = true;
+            condvar.notify_all();
+            let _ = done_tx.send(());
+        });
+
+        AsyncEventHandler {
+            queue,
+            command_rx,
+            done_rx,
+            thread: Some(thread),
+            panic: None,
+            proxy,
+            capacity: options.capacity,
+            overflow_policy: options.overflow_policy,
+            shutdown_timeout: options.shutdown_timeout,
+            coalesce: options.coalesce,
+        }
+    }
 
-            let mut handle_event = |event: Arc<Event>| match *event {
-                Event::Shutdown => {
-                    println!("Shutting down AsyncEventHandler");
-                    false
+    fn work(
+        mut event_handler: Box<EventHandler + Send>,
+        queue: &SharedQueue,
+        command_tx: &Sender<Result<Vec<Command>, String>>,
+        proxy: &SharedProxy,
+    ) {
+        let wakeup = || {
+            if let Some(proxy) = &*proxy.lock().unwrap() {
+                let _ = proxy.wakeup();
+            }
+        };
+        let (lock, condvar) = &**queue;
+        loop {
+            let event = {
+                let mut queue = lock.lock().unwrap();
+                loop {
+                    if let Some(event) = queue.events.pop_front() {
+                        condvar.notify_all();
+                        break event;
+                    }
+                    if queue.shutting_down {
+                        return;
+                    }
+                    queue = condvar.wait(queue).unwrap();
                 }
-                _ => send_commands(event_handler.handle_event(event)),
             };
 
-            let mut handle_message = |event: Result<Arc<Event>, RecvError>| match event {
-                Ok(event) => handle_event(event),
-                _ => panic!("Event sender in AsyncEventHandler hung up!"),
+            let result =
+                panic::catch_unwind(AssertUnwindSafe(|| event_handler.handle_event(event)));
+            let panicked = result.is_err();
+            let result = result.map_err(panic_message);
+            let has_commands = match &result {
+                Ok(commands) => !commands.is_empty(),
+                Err(_) => true,
             };
+            if command_tx.send(result).is_err() || panicked {
+                wakeup();
+                return;
+            }
+            if has_commands {
+                wakeup();
+            }
+        }
+    }
 
-            while handle_message(event_rx.recv()) {}
-        });
-        AsyncEventHandler {
-            event_tx,
-            command_rx,
+    pub fn dropped_events(&self) -> u64 {
+        self.queue.0.lock().unwrap().dropped
+    }
+
+    fn is_coalesced(&self, event: &Event) -> bool {
+        match &self.coalesce {
+            Some(coalesce) => coalesce(event),
+            None => false,
         }
     }
 
     fn send_event(&mut self, event: Arc<Event>) {
-        match self.event_tx.send(event) {
-            Ok(_) => (),
-            _ => panic!("Event receiver in AsyncEventHandler hung up!"),
+        let (lock, condvar) = &*self.queue;
+        let mut queue = lock.lock().unwrap();
+        if queue.closed || queue.shutting_down {
+            return;
+        }
+
+        if self.is_coalesced(&event) {
+            let kind = mem::discriminant(&*event);
+            if let Some(queued) = queue
+                .events
+                .iter_mut()
+                .rev()
+                .find(|queued| mem::discriminant(&***queued) == kind)
+            {
+                *queued = event;
+                return;
+            }
+        }
+
+        if let Some(capacity) = self.capacity {
+            match self.overflow_policy {
+                OverflowPolicy::Block => {
+                    while queue.events.len() >= capacity && !queue.closed {
+                        queue = condvar.wait(queue).unwrap();
+                    }
+                    if queue.closed {
+                        return;
+                    }
+                }
+                OverflowPolicy::DropNewest => {
+                    if queue.events.len() >= capacity {
+                        queue.dropped += 1;
+                        return;
+                    }
+                }
+                OverflowPolicy::DropOldest => {
+                    while queue.events.len() >= capacity {
+                        queue.events.pop_front();
+                        queue.dropped += 1;
+                    }
+                }
+            }
         }
+
+        queue.events.push_back(event);
+        condvar.notify_all();
     }
 
+    // A panic on the worker is resumed here so EventHandlers reports and removes this handler
     fn get_commands(&mut self) -> Vec<Command> {
+        if let Some(message) = self.panic.take() {
+            panic::resume_unwind(Box::new(message));
+        }
         let mut out = vec![];
-        loop {
-            match &mut self.command_rx.try_recv() {
-                Ok(commands) => out.append(commands),
-                Err(TryRecvError::Empty) => return out,
-                Err(TryRecvError::Disconnected) => {
-                    panic!("Command sender in AsyncEventHandler hung up!")
+        while let Ok(result) = self.command_rx.try_recv() {
+            match result {
+                Ok(mut commands) => out.append(&mut commands),
+                Err(message) if out.is_empty() => panic::resume_unwind(Box::new(message)),
+                Err(message) => self.panic = Some(message),
+            }
+        }
+        out
+    }
+
+    fn shutdown(&mut self) {
+        {
+            let (lock, condvar) = &*self.queue;
+            let mut queue = lock.lock().unwrap();
+            queue.shutting_down = true;
+            condvar.notify_all();
+        }
+        if let Some(thread) = self.thread.take() {
+            match self.done_rx.recv_timeout(self.shutdown_timeout) {
+                Ok(()) | Err(RecvTimeoutError::Disconnected) => {
+                    let _ = thread.join();
                 }
-            };
+                Err(RecvTimeoutError::Timeout) => (),
+            }
         }
     }
 }
 
+fn panic_message(payload: Box<Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Event handler panicked".to_string(),
+        },
+    }
+}
+
+impl Drop for AsyncEventHandler {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
 impl EventHandler for AsyncEventHandler {
     fn handle_event(&mut self, event: Arc<Event>) -> Vec<Command> {
         if let Event::Shutdown = *event {
-            self.send_event(event);
+            self.shutdown();
             vec![]
         } else {
             self.send_event(event);
             self.get_commands()
         }
     }
+
+    fn set_waker(&mut self, waker: glutin::EventsLoopProxy) {
+        let mut proxy = self.proxy.lock().unwrap();
+        if proxy.is_none() {
+            *proxy = Some(waker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Recorder {
+        name: &'static str,
+        consume: bool,
+        log: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl EventHandler for Recorder {
+        fn handle_event(&mut self, _: Arc<Event>) -> Vec<Command> {
+            self.log.lock().unwrap().push(self.name);
+            if self.consume {
+                vec![Command::RequestRedraw]
+            } else {
+                vec![]
+            }
+        }
+
+        fn handle_event_with_result(
+            &mut self,
+            event: Arc<Event>,
+            _: Option<&EngineView>,
+        ) -> HandlerResult {
+            HandlerResult {
+                commands: self.handle_event(event),
+                consume: self.consume,
+            }
+        }
+    }
+
+    fn handlers(log: &Arc<Mutex<Vec<&'static str>>>) -> EventHandlers {
+        let recorder = |name, consume| {
+            Box::new(Recorder {
+                name,
+                consume,
+                log: log.clone(),
+            })
+        };
+        let mut out = EventHandlers::new();
+        out.add(recorder("world", false), EventHandlers::DEFAULT_PRIORITY);
+        out.add(recorder("ui", true), 10);
+        out.add(recorder("camera", false), EventHandlers::DEFAULT_PRIORITY);
+        out.add(recorder("background", false), -10);
+        out
+    }
+
+    struct Panicked {}
+
+    impl EventHandler for Panicked {
+        fn handle_event(&mut self, _: Arc<Event>) -> Vec<Command> {
+            panic!("boom")
+        }
+    }
+
+    #[test]
+    fn test_panicked_handler_is_reported_with_id_and_removed() {
+        let mut handlers = EventHandlers::new();
+        let id = handlers.add(Box::new(Panicked {}), EventHandlers::DEFAULT_PRIORITY);
+
+        let commands = handlers.handle_event(Arc::new(Event::WorldDrawn));
+
+        match commands.as_slice() {
+            [Command::Event(Event::HandlerPanicked {
+                id: panicked,
+                message,
+            })] => {
+                assert_eq!(*panicked, id);
+                assert_eq!(message, "boom");
+            }
+            _ => panic!("Expected panic event"),
+        }
+        assert!(handlers.remove(id).is_none());
+    }
+
+    #[test]
+    fn test_consumed_events_do_not_reach_lower_priorities() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut handlers = handlers(&log);
+
+        let commands = handlers.handle_event(Arc::new(Event::WorldDrawn));
+
+        assert_eq!(*log.lock().unwrap(), vec!["ui"]);
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(commands[0], Command::RequestRedraw));
+    }
+
+    #[test]
+    fn test_remove_and_replace_handlers() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut handlers = EventHandlers::new();
+        let recorder = |name| {
+            Box::new(Recorder {
+                name,
+                consume: false,
+                log: log.clone(),
+            })
+        };
+        let first = handlers.add(recorder("first"), EventHandlers::DEFAULT_PRIORITY);
+        let second = handlers.add(recorder("second"), EventHandlers::DEFAULT_PRIORITY);
+
+        assert!(handlers.replace(first, recorder("replaced")).is_some());
+        assert!(handlers.remove(second).is_some());
+        assert!(handlers.remove(second).is_none());
+        handlers.handle_event(Arc::new(Event::WorldDrawn));
+
+        assert_eq!(*log.lock().unwrap(), vec!["replaced"]);
+    }
+
+    struct DropFlag {
+        dropped: Arc<Mutex<bool>>,
+    }
+
+    impl EventHandler for DropFlag {
+        fn handle_event(&mut self, _: Arc<Event>) -> Vec<Command> {
+            vec![]
+        }
+    }
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            *self.dropped.lock().unwrap() = true;
+        }
+    }
+
+    #[test]
+    fn test_removed_async_handler_thread_is_shut_down() {
+        let dropped = Arc::new(Mutex::new(false));
+        let mut handlers = EventHandlers::new();
+        let id = handlers.add(
+            Box::new(AsyncEventHandler::new(Box::new(DropFlag {
+                dropped: dropped.clone(),
+            }))),
+            EventHandlers::DEFAULT_PRIORITY,
+        );
+
+        drop(handlers.remove(id));
+
+        assert!(*dropped.lock().unwrap());
+    }
+
+    struct ViewReader {}
+
+    impl EventHandler for ViewReader {
+        fn handle_event(&mut self, _: Arc<Event>) -> Vec<Command> {
+            vec![]
+        }
+
+        fn handle_event_with_view(&mut self, _: Arc<Event>, view: &EngineView) -> Vec<Command> {
+            if view.has_drawing("square") {
+                vec![Command::RequestRedraw]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    #[test]
+    fn test_handlers_can_read_view() {
+        use graphics::device::RecordingDevice;
+        use graphics::drawing::{DescribedDrawing, DrawingDescription};
+        use graphics::engine::GraphicsEngine;
+
+        RecordingDevice::install();
+        let mut graphics =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        graphics.add_drawing(
+            "square".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription::plain(vec![]),
+                None,
+            )),
+        );
+        let mut handlers = EventHandlers::new();
+        handlers.add(Box::new(ViewReader {}), EventHandlers::DEFAULT_PRIORITY);
+
+        let with_view = handlers
+            .handle_event_with_view(Arc::new(Event::WorldDrawn), &EngineView::new(&graphics));
+        let without_view = handlers.handle_event(Arc::new(Event::WorldDrawn));
+
+        assert_eq!(with_view.len(), 1);
+        assert!(without_view.is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_reaches_every_handler_in_priority_order() {
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut handlers = handlers(&log);
+
+        handlers.handle_event(Arc::new(Event::Shutdown));
+
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["ui", "world", "camera", "background"]
+        );
+    }
+
+    struct Gated {
+        started: mpsc::Sender<()>,
+        gate: Arc<Mutex<()>>,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl EventHandler for Gated {
+        fn handle_event(&mut self, event: Arc<Event>) -> Vec<Command> {
+            let _ = self.started.send(());
+            let _gate = self.gate.lock().unwrap();
+            if let Event::TimerFired(ref id) = *event {
+                if id == "panic" {
+                    panic!("Handler failed");
+                }
+                self.log.lock().unwrap().push(id.clone());
+            }
+            vec![]
+        }
+    }
+
+    fn fire(handler: &mut AsyncEventHandler, id: &str) -> Vec<Command> {
+        handler.handle_event(Arc::new(Event::TimerFired(id.to_string())))
+    }
+
+    fn flood(options: AsyncOptions) -> (Vec<String>, u64) {
+        let (started, started_rx) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(()));
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut handler = AsyncEventHandler::with_options(
+            Box::new(Gated {
+                started,
+                gate: gate.clone(),
+                log: log.clone(),
+            }),
+            options,
+        );
+
+        let dropped = {
+            let _closed = gate.lock().unwrap();
+            fire(&mut handler, "a");
+            started_rx.recv().unwrap();
+            for id in ["b", "c", "d"].iter() {
+                fire(&mut handler, id);
+            }
+            handler.dropped_events()
+        };
+        drop(handler);
+
+        let log = log.lock().unwrap().clone();
+        (log, dropped)
+    }
+
+    #[test]
+    fn test_drop_oldest_when_full() {
+        let (log, dropped) =
+            flood(AsyncOptions::new().with_capacity(2, OverflowPolicy::DropOldest));
+
+        assert_eq!(log, vec!["a", "c", "d"]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn test_drop_newest_when_full() {
+        let (log, dropped) =
+            flood(AsyncOptions::new().with_capacity(2, OverflowPolicy::DropNewest));
+
+        assert_eq!(log, vec!["a", "b", "c"]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[test]
+    fn test_coalescing_keeps_latest_event() {
+        let (log, dropped) = flood(
+            AsyncOptions::new()
+                .with_coalescing(Box::new(|event| matches!(event, Event::TimerFired(..)))),
+        );
+
+        assert_eq!(log, vec!["a", "d"]);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn test_panic_is_reported_as_event() {
+        let (started, _started_rx) = mpsc::channel();
+        let mut handlers = EventHandlers::new();
+        let id = handlers.add(
+            Box::new(AsyncEventHandler::new(Box::new(Gated {
+                started,
+                gate: Arc::new(Mutex::new(())),
+                log: Arc::new(Mutex::new(vec![])),
+            }))),
+            EventHandlers::DEFAULT_PRIORITY,
+        );
+        let fire = |handlers: &mut EventHandlers, id: &str| {
+            handlers.handle_event(Arc::new(Event::TimerFired(id.to_string())))
+        };
+
+        let mut commands = fire(&mut handlers, "panic");
+        for _ in 0..100 {
+            if !commands.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+            commands = fire(&mut handlers, "ignored");
+        }
+
+        match commands.as_slice() {
+            [Command::Event(Event::HandlerPanicked {
+                id: panicked,
+                message,
+            })] => {
+                assert_eq!(*panicked, id);
+                assert_eq!(message, "Handler failed");
+            }
+            _ => panic!("Expected panic event"),
+        }
+        assert!(handlers.remove(id).is_none());
+    }
+
+    #[test]
+    fn test_events_after_shutdown_do_not_keep_worker_alive() {
+        let (started, started_rx) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(()));
+        let log = Arc::new(Mutex::new(vec![]));
+        let mut handler = AsyncEventHandler::with_options(
+            Box::new(Gated {
+                started,
+                gate: gate.clone(),
+                log: log.clone(),
+            }),
+            AsyncOptions::new()
+                .with_capacity(1, OverflowPolicy::DropOldest)
+                .with_shutdown_timeout(Duration::from_millis(10)),
+        );
+        let closed = gate.lock().unwrap();
+        fire(&mut handler, "a");
+        started_rx.recv().unwrap();
+
+        handler.handle_event(Arc::new(Event::Shutdown));
+        fire(&mut handler, "b");
+        fire(&mut handler, "c");
+        drop(closed);
+
+        assert_eq!(handler.done_rx.recv_timeout(Duration::from_secs(1)), Ok(()));
+        assert_eq!(*log.lock().unwrap(), vec!["a"]);
+    }
+
+    #[test]
+    fn test_shutdown_times_out() {
+        let (started, started_rx) = mpsc::channel();
+        let gate = Arc::new(Mutex::new(()));
+        let mut handler = AsyncEventHandler::with_options(
+            Box::new(Gated {
+                started,
+                gate: gate.clone(),
+                log: Arc::new(Mutex::new(vec![])),
+            }),
+            AsyncOptions::new().with_shutdown_timeout(Duration::from_millis(10)),
+        );
+        let closed = gate.lock().unwrap();
+        fire(&mut handler, "a");
+        started_rx.recv().unwrap();
+
+        handler.handle_event(Arc::new(Event::Shutdown));
+
+        assert!(handler.thread.is_none());
+        drop(closed);
+    }
 }
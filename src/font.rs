@@ -1,6 +1,8 @@
-use graphics::texture::Texture;
+use error::Error;
+use image::RgbaImage;
 use std::fs::File;
 use std::io::Read;
+use std::sync::Arc;
 use {v2, V2};
 
 #[derive(Clone, Copy)]
@@ -16,53 +18,75 @@ struct Glyph {
 }
 
 impl Glyph {
-    fn from_line(line: &str) -> Glyph {
-        let columns: Vec<&str> = line.split(",").collect();
-        let id: usize = columns[0].parse().unwrap();
-        Glyph {
-            character: id as u8 as char,
-            x: columns[1].parse().unwrap(),
-            y: columns[2].parse().unwrap(),
-            width: columns[3].parse().unwrap(),
-            height: columns[4].parse().unwrap(),
-            xoffset: columns[5].parse().unwrap(),
-            yoffset: columns[6].parse().unwrap(),
-            xadvance: columns[7].parse().unwrap(),
+    fn from_line(line: &str) -> Result<Glyph, Error> {
+        let columns: Vec<&str> = line.split(",").map(|column| column.trim()).collect();
+        if columns.len() < 8 {
+            return Err(Error::Font(format!(
+                "Expected 8 columns but found {} in line [{}]",
+                columns.len(),
+                line
+            )));
+        }
+        let parse = |index: usize| {
+            columns[index].parse::<i32>().map_err(|_| {
+                Error::Font(format!(
+                    "Could not parse column {} in line [{}]",
+                    index, line
+                ))
+            })
+        };
+        let id = parse(0)?;
+        if !(0..=255).contains(&id) {
+            return Err(Error::Font(format!(
+                "Character {} in line [{}] is outside the first 256 characters",
+                id, line
+            )));
         }
+        Ok(Glyph {
+            character: id as u8 as char,
+            x: parse(1)?,
+            y: parse(2)?,
+            width: parse(3)?,
+            height: parse(4)?,
+            xoffset: parse(5)?,
+            yoffset: parse(6)?,
+            xadvance: parse(7)?,
+        })
     }
 
-    pub fn from_csv(file_name: &str) -> [Option<Glyph>; 256] {
-        let mut file = File::open(file_name).expect(&format!("Font file {} not found", file_name));
+    pub fn from_csv(file_name: &str) -> Result<[Option<Glyph>; 256], Error> {
+        let mut file = File::open(file_name)
+            .map_err(|err| Error::Font(format!("Font file {}: {}", file_name, err)))?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)
-            .expect(&format!("Failed to read font file {}", file_name));
+            .map_err(|err| Error::Font(format!("Font file {}: {}", file_name, err)))?;
 
         let mut glyphs = [None; 256];
 
-        for line in contents.split("\n") {
-            let glyph = Glyph::from_line(line);
+        for line in contents.split("\n").filter(|line| !line.trim().is_empty()) {
+            let glyph = Glyph::from_line(line)?;
             glyphs[glyph.character as usize] = Some(glyph);
         }
 
-        glyphs
+        Ok(glyphs)
     }
 }
 
 pub struct Font {
     glyphs: [Option<Glyph>; 256],
-    texture: Texture,
+    image: Arc<RgbaImage>,
 }
 
 impl Font {
-    pub fn from_csv_and_texture(csv_file_name: &str, texture: Texture) -> Font {
-        Font {
-            glyphs: Glyph::from_csv(csv_file_name),
-            texture,
-        }
+    pub fn from_csv_and_image(csv_file_name: &str, image: RgbaImage) -> Result<Font, Error> {
+        Ok(Font {
+            glyphs: Glyph::from_csv(csv_file_name)?,
+            image: Arc::new(image),
+        })
     }
 
-    pub fn texture(&self) -> &Texture {
-        &self.texture
+    pub fn image(&self) -> &Arc<RgbaImage> {
+        &self.image
     }
 
     fn get_glyph(&self, character: char) -> Glyph {
@@ -86,9 +110,15 @@ impl Font {
     pub fn get_texture_coords(&self, character: char) -> (V2<f32>, V2<f32>) {
         let glyph = self.get_glyph(character);
         (
-            self.texture.get_texture_coords(v2(glyph.x, glyph.y)),
-            self.texture
-                .get_texture_coords(v2(glyph.x + glyph.width, glyph.y + glyph.height)),
+            self.get_image_coords(v2(glyph.x, glyph.y)),
+            self.get_image_coords(v2(glyph.x + glyph.width, glyph.y + glyph.height)),
+        )
+    }
+
+    fn get_image_coords(&self, pixel_position: V2<i32>) -> V2<f32> {
+        v2(
+            pixel_position.x as f32 / self.image.width() as f32,
+            pixel_position.y as f32 / self.image.height() as f32,
         )
     }
 
@@ -105,3 +135,33 @@ impl Font {
         (glyph.xoffset, glyph.yoffset)
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_glyph_from_line() {
+        let glyph = Glyph::from_line("65,1,2,3,4,5,6,7").unwrap();
+
+        assert_eq!(glyph.character, 'A');
+        assert_eq!((glyph.x, glyph.y, glyph.width, glyph.height), (1, 2, 3, 4));
+        assert_eq!((glyph.xoffset, glyph.yoffset, glyph.xadvance), (5, 6, 7));
+    }
+
+    #[test]
+    fn test_malformed_glyph_line_is_error() {
+        assert!(Glyph::from_line("65,1,2").is_err());
+        assert!(Glyph::from_line("65,1,2,3,4,5,6,x").is_err());
+        assert!(Glyph::from_line("256,1,2,3,4,5,6,7").is_err());
+    }
+
+    #[test]
+    fn test_missing_font_file_is_error() {
+        match Glyph::from_csv("no-such-font.csv") {
+            Err(Error::Font(_)) => (),
+            _ => panic!("Expected font error"),
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use coords::*;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: WorldCoord,
+    pub max: WorldCoord,
+}
+
+impl BoundingBox {
+    pub fn new(min: WorldCoord, max: WorldCoord) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    pub fn from_vertices(vertices: &[f32], floats_per_vertex: usize) -> Option<BoundingBox> {
+        let mut out: Option<BoundingBox> = None;
+        for vertex in vertices.chunks(floats_per_vertex) {
+            let point = WorldCoord::new(vertex[0], vertex[1], vertex[2]);
+            out = match out {
+                Some(bounds) => Some(bounds.expand(point)),
+                None => Some(BoundingBox::new(point, point)),
+            };
+        }
+        out
+    }
+
+    pub fn expand(&self, point: WorldCoord) -> BoundingBox {
+        BoundingBox {
+            min: WorldCoord::new(
+                self.min.x.min(point.x),
+                self.min.y.min(point.y),
+                self.min.z.min(point.z),
+            ),
+            max: WorldCoord::new(
+                self.max.x.max(point.x),
+                self.max.y.max(point.y),
+                self.max.z.max(point.z),
+            ),
+        }
+    }
+
+    pub fn corners(&self) -> [WorldCoord; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            WorldCoord::new(a.x, a.y, a.z),
+            WorldCoord::new(b.x, a.y, a.z),
+            WorldCoord::new(a.x, b.y, a.z),
+            WorldCoord::new(b.x, b.y, a.z),
+            WorldCoord::new(a.x, a.y, b.z),
+            WorldCoord::new(b.x, a.y, b.z),
+            WorldCoord::new(a.x, b.y, b.z),
+            WorldCoord::new(b.x, b.y, b.z),
+        ]
+    }
+}
+
+pub struct ViewFrustum {
+    transform_matrix: na::Matrix4<f32>,
+}
+
+impl ViewFrustum {
+    pub fn new(transform_matrix: na::Matrix4<f32>) -> ViewFrustum {
+        ViewFrustum { transform_matrix }
+    }
+
+    fn project(&self, world_coord: WorldCoord) -> GLCoord4D {
+        let point: na::Point4<f32> = world_coord.into();
+        (self.transform_matrix * point).into()
+    }
+
+    pub fn is_visible(&self, bounds: &BoundingBox) -> bool {
+        self.is_visible_with_z_mod(bounds, 0.0)
+    }
+
+    // z_mod is added to gl_Position.z by the shaders, so the projected depth range is widened by it
+    pub fn is_visible_with_z_mod(&self, bounds: &BoundingBox, z_mod: f32) -> bool {
+        let mut min = GLCoord4D::new(f32::MAX, f32::MAX, f32::MAX, 1.0);
+        let mut max = GLCoord4D::new(f32::MIN, f32::MIN, f32::MIN, 1.0);
+        for corner in bounds.corners().iter() {
+            let projected = self.project(*corner);
+            min.x = min.x.min(projected.x);
+            min.y = min.y.min(projected.y);
+            min.z = min.z.min(projected.z);
+            max.x = max.x.max(projected.x);
+            max.y = max.y.max(projected.y);
+            max.z = max.z.max(projected.z);
+        }
+        min.z += z_mod.min(0.0);
+        max.z += z_mod.max(0.0);
+        !(max.x < -1.0 || min.x > 1.0 || max.y < -1.0 || min.y > 1.0 || max.z < -1.0 || min.z > 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use transform::{Identity, Transform};
+
+    fn frustum() -> ViewFrustum {
+        let transform = Transform::new(
+            GLCoord3D::new(1.0, 1.0, 1.0),
+            GLCoord2D::new(0.0, 0.0),
+            Identity::boxed(),
+        );
+        ViewFrustum::new(transform.compute_transformation_matrix())
+    }
+
+    #[test]
+    fn test_bounding_box_from_vertices() {
+        #[rustfmt::skip]
+        let vertices = vec![
+            1.0, 5.0, -2.0, 0.0, 0.0, 0.0,
+            -3.0, 2.0, 4.0, 0.0, 0.0, 0.0,
+            2.0, -1.0, 0.0, 0.0, 0.0, 0.0,
+        ];
+
+        assert_eq!(
+            BoundingBox::from_vertices(&vertices, 6),
+            Some(BoundingBox::new(
+                WorldCoord::new(-3.0, -1.0, -2.0),
+                WorldCoord::new(2.0, 5.0, 4.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_bounding_box_from_no_vertices() {
+        assert_eq!(BoundingBox::from_vertices(&[], 6), None);
+    }
+
+    #[test]
+    fn test_bounds_inside_viewport_are_visible() {
+        let bounds = BoundingBox::new(
+            WorldCoord::new(-0.5, -0.5, -0.5),
+            WorldCoord::new(0.5, 0.5, 0.5),
+        );
+        assert!(frustum().is_visible(&bounds));
+    }
+
+    #[test]
+    fn test_bounds_overlapping_viewport_edge_are_visible() {
+        let bounds = BoundingBox::new(
+            WorldCoord::new(0.5, 0.5, 0.0),
+            WorldCoord::new(1.5, 1.5, 0.0),
+        );
+        assert!(frustum().is_visible(&bounds));
+    }
+
+    #[test]
+    fn test_bounds_outside_viewport_are_not_visible() {
+        let right = BoundingBox::new(
+            WorldCoord::new(1.5, 0.0, 0.0),
+            WorldCoord::new(2.0, 0.0, 0.0),
+        );
+        let below = BoundingBox::new(
+            WorldCoord::new(0.0, -2.0, 0.0),
+            WorldCoord::new(0.0, -1.5, 0.0),
+        );
+        let behind = BoundingBox::new(
+            WorldCoord::new(0.0, 0.0, 1.5),
+            WorldCoord::new(0.0, 0.0, 2.0),
+        );
+        assert!(!frustum().is_visible(&right));
+        assert!(!frustum().is_visible(&below));
+        assert!(!frustum().is_visible(&behind));
+    }
+
+    #[test]
+    fn test_z_mod_widens_depth_range() {
+        let behind = BoundingBox::new(
+            WorldCoord::new(0.0, 0.0, 1.5),
+            WorldCoord::new(0.0, 0.0, 2.0),
+        );
+        let in_front = BoundingBox::new(
+            WorldCoord::new(0.0, 0.0, -2.0),
+            WorldCoord::new(0.0, 0.0, -1.5),
+        );
+        assert!(frustum().is_visible_with_z_mod(&behind, -1.0));
+        assert!(!frustum().is_visible_with_z_mod(&behind, 0.25));
+        assert!(frustum().is_visible_with_z_mod(&in_front, 1.0));
+        assert!(!frustum().is_visible_with_z_mod(&in_front, -0.25));
+    }
+
+    #[test]
+    fn test_visibility_follows_transform() {
+        let transform = Transform::new(
+            GLCoord3D::new(1.0, 1.0, 1.0),
+            GLCoord2D::new(-2.0, 0.0),
+            Identity::boxed(),
+        );
+        let frustum = ViewFrustum::new(transform.compute_transformation_matrix());
+        let bounds = BoundingBox::new(
+            WorldCoord::new(1.5, 0.0, 0.0),
+            WorldCoord::new(2.0, 0.0, 0.0),
+        );
+        assert!(frustum.is_visible(&bounds));
+    }
+}
@@ -0,0 +1,987 @@
+use image::RgbaImage;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_void, CString};
+use std::rc::Rc;
+use utils::create_whitespace_cstring_with_len;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BufferTarget {
+    Array,
+    ElementArray,
+    CopyRead,
+    CopyWrite,
+    PixelPack,
+}
+
+impl BufferTarget {
+    fn gl_enum(&self) -> gl::types::GLenum {
+        match self {
+            BufferTarget::Array => gl::ARRAY_BUFFER,
+            BufferTarget::ElementArray => gl::ELEMENT_ARRAY_BUFFER,
+            BufferTarget::CopyRead => gl::COPY_READ_BUFFER,
+            BufferTarget::CopyWrite => gl::COPY_WRITE_BUFFER,
+            BufferTarget::PixelPack => gl::PIXEL_PACK_BUFFER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VertexAttribute {
+    pub index: u32,
+    pub floats: usize,
+    pub stride: usize,
+    pub offset: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uniform {
+    Float(f32),
+    Matrix2(na::Matrix2<f32>),
+    Matrix3(na::Matrix3<f32>),
+    Matrix4(na::Matrix4<f32>),
+}
+
+// Only implemented for types without padding, so every byte of a slice is initialized
+pub(crate) trait BufferElement {}
+
+impl BufferElement for f32 {}
+impl BufferElement for u32 {}
+
+pub(crate) fn as_bytes<T: BufferElement>(values: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+pub trait RenderDevice {
+    fn create_buffer(&self) -> u32;
+    fn delete_buffer(&self, buffer: u32);
+    fn bind_buffer(&self, target: BufferTarget, buffer: u32);
+    fn buffer_data(&self, target: BufferTarget, bytes: usize, data: Option<&[u8]>);
+    fn buffer_sub_data(&self, target: BufferTarget, offset: usize, data: &[u8]);
+    fn copy_buffer_sub_data(&self, from: usize, to: usize, bytes: usize);
+    fn get_buffer_sub_data(&self, target: BufferTarget, offset: usize, bytes: usize) -> Vec<u8>;
+
+    fn create_vertex_array(&self) -> u32;
+    fn delete_vertex_array(&self, vertex_array: u32);
+    fn bind_vertex_array(&self, vertex_array: u32);
+    fn vertex_attribute(&self, attribute: VertexAttribute);
+
+    fn create_texture(&self) -> u32;
+    fn delete_texture(&self, texture: u32);
+    fn bind_texture(&self, texture: u32);
+    fn texture_image(&self, width: u32, height: u32, pixels: &[u8]);
+
+    fn create_framebuffer(&self) -> u32;
+    fn delete_framebuffer(&self, framebuffer: u32);
+    fn bind_framebuffer(&self, framebuffer: u32);
+    fn framebuffer_binding(&self) -> u32;
+    fn create_depth_buffer(&self, width: u32, height: u32) -> u32;
+    fn delete_depth_buffer(&self, depth_buffer: u32);
+    fn attach_to_framebuffer(&self, texture: u32, depth_buffer: u32) -> Result<(), u32>;
+    fn create_color_buffer(&self, width: u32, height: u32) -> u32;
+    fn delete_color_buffer(&self, color_buffer: u32);
+    fn attach_buffers_to_framebuffer(
+        &self,
+        color_buffer: u32,
+        depth_buffer: u32,
+    ) -> Result<(), u32>;
+
+    fn create_program(&self, vertex_shader: &str, fragment_shader: &str) -> Result<u32, String>;
+    fn delete_program(&self, program: u32);
+    fn use_program(&self, program: u32);
+    fn uniform(&self, program: u32, name: &str, value: Uniform);
+
+    fn draw_arrays(&self, vertices: usize);
+    fn draw_elements(&self, elements: usize);
+    fn draw_elements_base_vertex(&self, elements: usize, offset: usize, base_vertex: usize);
+
+    fn setup(&self);
+    fn set_viewport(&self, width: i32, height: i32, clear_color: [f32; 4]);
+    fn clear(&self);
+    fn read_depth(&self, x: i32, y: i32) -> f32;
+    fn read_pixels(&self, width: u32, height: u32) -> RgbaImage;
+    fn read_pixels_to_buffer(&self, width: u32, height: u32);
+}
+
+thread_local! {
+    static DEVICE: RefCell<Rc<RenderDevice>> = RefCell::new(Rc::new(GLDevice {}));
+}
+
+pub fn device() -> Rc<RenderDevice> {
+    DEVICE.with(|device| device.borrow().clone())
+}
+
+pub fn set_device(device: Rc<RenderDevice>) {
+    DEVICE.with(|current| *current.borrow_mut() = device);
+}
+
+pub struct GLDevice {}
+
+impl GLDevice {
+    unsafe fn framebuffer_status() -> Result<(), u32> {
+        match gl::CheckFramebufferStatus(gl::FRAMEBUFFER) {
+            gl::FRAMEBUFFER_COMPLETE => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    fn program_message(program: u32) -> String {
+        let mut length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        let error = create_whitespace_cstring_with_len(length as usize);
+        unsafe {
+            gl::GetProgramInfoLog(
+                program,
+                length,
+                std::ptr::null_mut(),
+                error.as_ptr() as *mut gl::types::GLchar,
+            );
+        }
+        error.to_string_lossy().into_owned()
+    }
+
+    fn shader_message(shader: u32) -> String {
+        let mut length: gl::types::GLint = 0;
+        unsafe {
+            gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length);
+        }
+        let error = create_whitespace_cstring_with_len(length as usize);
+        unsafe {
+            gl::GetShaderInfoLog(
+                shader,
+                length,
+                std::ptr::null_mut(),
+                error.as_ptr() as *mut gl::types::GLchar,
+            );
+        }
+        error.to_string_lossy().into_owned()
+    }
+
+    fn compile_shader(source: &str, kind: gl::types::GLenum) -> Result<u32, String> {
+        let source = CString::new(source).map_err(|err| err.to_string())?;
+        let id = unsafe { gl::CreateShader(kind) };
+        let mut success: gl::types::GLint = 1;
+        unsafe {
+            gl::ShaderSource(id, 1, &source.as_ptr(), std::ptr::null());
+            gl::CompileShader(id);
+            gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        }
+        if success == 0 {
+            let message = GLDevice::shader_message(id);
+            unsafe {
+                gl::DeleteShader(id);
+            }
+            return Err(message);
+        }
+        Ok(id)
+    }
+
+    fn uniform_location(program: u32, name: &str) -> gl::types::GLint {
+        let name = CString::new(name).unwrap();
+        unsafe { gl::GetUniformLocation(program, name.as_ptr() as *const gl::types::GLchar) }
+    }
+}
+
+impl RenderDevice for GLDevice {
+    fn create_buffer(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+        }
+        id
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        unsafe {
+            gl::DeleteBuffers(1, &buffer);
+        }
+    }
+
+    fn bind_buffer(&self, target: BufferTarget, buffer: u32) {
+        unsafe {
+            gl::BindBuffer(target.gl_enum(), buffer);
+        }
+    }
+
+    fn buffer_data(&self, target: BufferTarget, bytes: usize, data: Option<&[u8]>) {
+        unsafe {
+            gl::BufferData(
+                target.gl_enum(),
+                bytes as gl::types::GLsizeiptr,
+                data.map(|data| data.as_ptr() as *const c_void)
+                    .unwrap_or(std::ptr::null()),
+                gl::STATIC_DRAW,
+            );
+        }
+    }
+
+    fn buffer_sub_data(&self, target: BufferTarget, offset: usize, data: &[u8]) {
+        unsafe {
+            gl::BufferSubData(
+                target.gl_enum(),
+                offset as gl::types::GLintptr,
+                data.len() as gl::types::GLsizeiptr,
+                data.as_ptr() as *const c_void,
+            );
+        }
+    }
+
+    fn copy_buffer_sub_data(&self, from: usize, to: usize, bytes: usize) {
+        unsafe {
+            gl::CopyBufferSubData(
+                gl::COPY_READ_BUFFER,
+                gl::COPY_WRITE_BUFFER,
+                from as gl::types::GLintptr,
+                to as gl::types::GLintptr,
+                bytes as gl::types::GLsizeiptr,
+            );
+        }
+    }
+
+    fn get_buffer_sub_data(&self, target: BufferTarget, offset: usize, bytes: usize) -> Vec<u8> {
+        let mut out: Vec<u8> = vec![0; bytes];
+        unsafe {
+            gl::GetBufferSubData(
+                target.gl_enum(),
+                offset as gl::types::GLintptr,
+                bytes as gl::types::GLsizeiptr,
+                out.as_mut_ptr() as *mut c_void,
+            );
+        }
+        out
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut id);
+        }
+        id
+    }
+
+    fn delete_vertex_array(&self, vertex_array: u32) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &vertex_array);
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: u32) {
+        unsafe {
+            gl::BindVertexArray(vertex_array);
+        }
+    }
+
+    fn vertex_attribute(&self, attribute: VertexAttribute) {
+        unsafe {
+            gl::EnableVertexAttribArray(attribute.index);
+            gl::VertexAttribPointer(
+                attribute.index,
+                attribute.floats as gl::types::GLint,
+                gl::FLOAT,
+                gl::FALSE,
+                (attribute.stride * std::mem::size_of::<f32>()) as gl::types::GLint,
+                (attribute.offset * std::mem::size_of::<f32>()) as *const gl::types::GLvoid,
+            );
+        }
+    }
+
+    fn create_texture(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+        }
+        id
+    }
+
+    fn delete_texture(&self, texture: u32) {
+        unsafe {
+            gl::DeleteTextures(1, &texture);
+        }
+    }
+
+    fn bind_texture(&self, texture: u32) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+        }
+    }
+
+    fn texture_image(&self, width: u32, height: u32, pixels: &[u8]) {
+        unsafe {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA as i32,
+                width as i32,
+                height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr() as *const c_void,
+            );
+        }
+    }
+
+    fn create_framebuffer(&self) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        id
+    }
+
+    fn delete_framebuffer(&self, framebuffer: u32) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &framebuffer);
+        }
+    }
+
+    fn bind_framebuffer(&self, framebuffer: u32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+        }
+    }
+
+    fn framebuffer_binding(&self) -> u32 {
+        let mut id: gl::types::GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut id);
+        }
+        id as u32
+    }
+
+    fn create_depth_buffer(&self, width: u32, height: u32) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorage(
+                gl::RENDERBUFFER,
+                gl::DEPTH_COMPONENT24,
+                width as i32,
+                height as i32,
+            );
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        id
+    }
+
+    fn delete_depth_buffer(&self, depth_buffer: u32) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &depth_buffer);
+        }
+    }
+
+    fn attach_to_framebuffer(&self, texture: u32, depth_buffer: u32) -> Result<(), u32> {
+        unsafe {
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::TEXTURE_2D,
+                texture,
+                0,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth_buffer,
+            );
+            GLDevice::framebuffer_status()
+        }
+    }
+
+    fn create_color_buffer(&self, width: u32, height: u32) -> u32 {
+        let mut id: gl::types::GLuint = 0;
+        unsafe {
+            gl::GenRenderbuffers(1, &mut id);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::RGBA8, width as i32, height as i32);
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+        }
+        id
+    }
+
+    fn delete_color_buffer(&self, color_buffer: u32) {
+        unsafe {
+            gl::DeleteRenderbuffers(1, &color_buffer);
+        }
+    }
+
+    fn attach_buffers_to_framebuffer(
+        &self,
+        color_buffer: u32,
+        depth_buffer: u32,
+    ) -> Result<(), u32> {
+        unsafe {
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                gl::RENDERBUFFER,
+                color_buffer,
+            );
+            gl::FramebufferRenderbuffer(
+                gl::FRAMEBUFFER,
+                gl::DEPTH_ATTACHMENT,
+                gl::RENDERBUFFER,
+                depth_buffer,
+            );
+            GLDevice::framebuffer_status()
+        }
+    }
+
+    fn create_program(&self, vertex_shader: &str, fragment_shader: &str) -> Result<u32, String> {
+        let vertex_shader = GLDevice::compile_shader(vertex_shader, gl::VERTEX_SHADER)?;
+        let fragment_shader = match GLDevice::compile_shader(fragment_shader, gl::FRAGMENT_SHADER) {
+            Ok(shader) => shader,
+            Err(message) => {
+                unsafe {
+                    gl::DeleteShader(vertex_shader);
+                }
+                return Err(message);
+            }
+        };
+        let shaders = [vertex_shader, fragment_shader];
+        let id = unsafe { gl::CreateProgram() };
+        let mut success: gl::types::GLint = 1;
+        unsafe {
+            for shader in shaders.iter() {
+                gl::AttachShader(id, *shader);
+            }
+            gl::LinkProgram(id);
+            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+            for shader in shaders.iter() {
+                gl::DetachShader(id, *shader);
+                gl::DeleteShader(*shader);
+            }
+        }
+        if success == 0 {
+            let message = GLDevice::program_message(id);
+            self.delete_program(id);
+            return Err(message);
+        }
+        Ok(id)
+    }
+
+    fn delete_program(&self, program: u32) {
+        unsafe {
+            gl::DeleteProgram(program);
+        }
+    }
+
+    fn use_program(&self, program: u32) {
+        unsafe {
+            gl::UseProgram(program);
+        }
+    }
+
+    fn uniform(&self, program: u32, name: &str, value: Uniform) {
+        let location = GLDevice::uniform_location(program, name);
+        unsafe {
+            match value {
+                Uniform::Float(float) => gl::Uniform1f(location, float),
+                Uniform::Matrix2(matrix) => {
+                    gl::UniformMatrix2fv(location, 1, gl::FALSE, matrix.as_slice().as_ptr())
+                }
+                Uniform::Matrix3(matrix) => {
+                    gl::UniformMatrix3fv(location, 1, gl::FALSE, matrix.as_slice().as_ptr())
+                }
+                Uniform::Matrix4(matrix) => {
+                    gl::UniformMatrix4fv(location, 1, gl::FALSE, matrix.as_slice().as_ptr())
+                }
+            }
+        }
+    }
+
+    fn draw_arrays(&self, vertices: usize) {
+        unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, vertices as i32);
+        }
+    }
+
+    fn draw_elements(&self, elements: usize) {
+        unsafe {
+            gl::DrawElements(
+                gl::TRIANGLES,
+                elements as i32,
+                gl::UNSIGNED_INT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    fn draw_elements_base_vertex(&self, elements: usize, offset: usize, base_vertex: usize) {
+        unsafe {
+            gl::DrawElementsBaseVertex(
+                gl::TRIANGLES,
+                elements as i32,
+                gl::UNSIGNED_INT,
+                offset as *const gl::types::GLvoid,
+                base_vertex as i32,
+            );
+        }
+    }
+
+    fn setup(&self) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+    }
+
+    fn set_viewport(&self, width: i32, height: i32, clear_color: [f32; 4]) {
+        unsafe {
+            gl::Viewport(0, 0, width, height);
+            gl::ClearColor(
+                clear_color[0],
+                clear_color[1],
+                clear_color[2],
+                clear_color[3],
+            );
+        }
+    }
+
+    fn clear(&self) {
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+    }
+
+    fn read_depth(&self, x: i32, y: i32) -> f32 {
+        let mut buffer: Vec<f32> = vec![0.0];
+        unsafe {
+            gl::ReadPixels(
+                x,
+                y,
+                1,
+                1,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                buffer.as_mut_ptr() as *mut c_void,
+            );
+        }
+        buffer[0]
+    }
+
+    fn read_pixels(&self, width: u32, height: u32) -> RgbaImage {
+        let mut pixels: Vec<u8> = vec![0; width as usize * height as usize * 4];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut c_void,
+            );
+        }
+        let pixels = flip_rows(pixels, width as usize * 4);
+        RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+
+    fn read_pixels_to_buffer(&self, width: u32, height: u32) {
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null_mut(),
+            );
+        }
+    }
+}
+
+fn flip_rows(pixels: Vec<u8>, row_length: usize) -> Vec<u8> {
+    pixels
+        .chunks(row_length)
+        .rev()
+        .flat_map(|row| row.iter().cloned())
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Call {
+    CreateBuffer(u32),
+    DeleteBuffer(u32),
+    BindBuffer(BufferTarget, u32),
+    BufferData {
+        target: BufferTarget,
+        bytes: usize,
+        data: Option<Vec<u8>>,
+    },
+    BufferSubData {
+        target: BufferTarget,
+        offset: usize,
+        data: Vec<u8>,
+    },
+    CopyBufferSubData {
+        from: usize,
+        to: usize,
+        bytes: usize,
+    },
+    GetBufferSubData {
+        target: BufferTarget,
+        offset: usize,
+        bytes: usize,
+    },
+    CreateVertexArray(u32),
+    DeleteVertexArray(u32),
+    BindVertexArray(u32),
+    VertexAttribute(VertexAttribute),
+    CreateTexture(u32),
+    DeleteTexture(u32),
+    BindTexture(u32),
+    TextureImage {
+        width: u32,
+        height: u32,
+    },
+    CreateFramebuffer(u32),
+    DeleteFramebuffer(u32),
+    BindFramebuffer(u32),
+    CreateDepthBuffer {
+        id: u32,
+        width: u32,
+        height: u32,
+    },
+    DeleteDepthBuffer(u32),
+    AttachToFramebuffer {
+        texture: u32,
+        depth_buffer: u32,
+    },
+    CreateColorBuffer {
+        id: u32,
+        width: u32,
+        height: u32,
+    },
+    DeleteColorBuffer(u32),
+    AttachBuffersToFramebuffer {
+        color_buffer: u32,
+        depth_buffer: u32,
+    },
+    CreateProgram(u32),
+    DeleteProgram(u32),
+    UseProgram(u32),
+    Uniform {
+        program: u32,
+        name: String,
+        value: Uniform,
+    },
+    DrawArrays(usize),
+    DrawElements(usize),
+    DrawElementsBaseVertex {
+        elements: usize,
+        offset: usize,
+        base_vertex: usize,
+    },
+    Setup,
+    SetViewport(i32, i32),
+    Clear,
+    ReadDepth(i32, i32),
+    ReadPixels(u32, u32),
+    ReadPixelsToBuffer(u32, u32),
+}
+
+pub struct RecordingDevice {
+    calls: RefCell<Vec<Call>>,
+    next_id: Cell<u32>,
+    depth: Cell<f32>,
+    framebuffer: Cell<u32>,
+}
+
+impl RecordingDevice {
+    pub fn new() -> RecordingDevice {
+        RecordingDevice {
+            calls: RefCell::new(vec![]),
+            next_id: Cell::new(1),
+            depth: Cell::new(1.0),
+            framebuffer: Cell::new(0),
+        }
+    }
+
+    pub fn install() -> Rc<RecordingDevice> {
+        let out = Rc::new(RecordingDevice::new());
+        set_device(out.clone());
+        out
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.calls.borrow_mut().clear();
+    }
+
+    pub fn set_depth(&self, depth: f32) {
+        self.depth.set(depth);
+    }
+
+    pub fn draw_calls(&self) -> Vec<Call> {
+        self.calls
+            .borrow()
+            .iter()
+            .filter(|call| {
+                matches!(
+                    call,
+                    Call::DrawArrays(..)
+                        | Call::DrawElements(..)
+                        | Call::DrawElementsBaseVertex { .. }
+                )
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn uniforms(&self) -> Vec<(u32, String, Uniform)> {
+        self.calls
+            .borrow()
+            .iter()
+            .filter_map(|call| match call {
+                Call::Uniform {
+                    program,
+                    name,
+                    value,
+                } => Some((*program, name.clone(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn record(&self, call: Call) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    fn create(&self, call: fn(u32) -> Call) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.record(call(id));
+        id
+    }
+}
+
+impl Default for RecordingDevice {
+    fn default() -> RecordingDevice {
+        RecordingDevice::new()
+    }
+}
+
+impl RenderDevice for RecordingDevice {
+    fn create_buffer(&self) -> u32 {
+        self.create(Call::CreateBuffer)
+    }
+
+    fn delete_buffer(&self, buffer: u32) {
+        self.record(Call::DeleteBuffer(buffer));
+    }
+
+    fn bind_buffer(&self, target: BufferTarget, buffer: u32) {
+        self.record(Call::BindBuffer(target, buffer));
+    }
+
+    fn buffer_data(&self, target: BufferTarget, bytes: usize, data: Option<&[u8]>) {
+        self.record(Call::BufferData {
+            target,
+            bytes,
+            data: data.map(|data| data.to_vec()),
+        });
+    }
+
+    fn buffer_sub_data(&self, target: BufferTarget, offset: usize, data: &[u8]) {
+        self.record(Call::BufferSubData {
+            target,
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    fn copy_buffer_sub_data(&self, from: usize, to: usize, bytes: usize) {
+        self.record(Call::CopyBufferSubData { from, to, bytes });
+    }
+
+    fn get_buffer_sub_data(&self, target: BufferTarget, offset: usize, bytes: usize) -> Vec<u8> {
+        self.record(Call::GetBufferSubData {
+            target,
+            offset,
+            bytes,
+        });
+        vec![0; bytes]
+    }
+
+    fn create_vertex_array(&self) -> u32 {
+        self.create(Call::CreateVertexArray)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: u32) {
+        self.record(Call::DeleteVertexArray(vertex_array));
+    }
+
+    fn bind_vertex_array(&self, vertex_array: u32) {
+        self.record(Call::BindVertexArray(vertex_array));
+    }
+
+    fn vertex_attribute(&self, attribute: VertexAttribute) {
+        self.record(Call::VertexAttribute(attribute));
+    }
+
+    fn create_texture(&self) -> u32 {
+        self.create(Call::CreateTexture)
+    }
+
+    fn delete_texture(&self, texture: u32) {
+        self.record(Call::DeleteTexture(texture));
+    }
+
+    fn bind_texture(&self, texture: u32) {
+        self.record(Call::BindTexture(texture));
+    }
+
+    fn texture_image(&self, width: u32, height: u32, _: &[u8]) {
+        self.record(Call::TextureImage { width, height });
+    }
+
+    fn create_framebuffer(&self) -> u32 {
+        self.create(Call::CreateFramebuffer)
+    }
+
+    fn delete_framebuffer(&self, framebuffer: u32) {
+        self.record(Call::DeleteFramebuffer(framebuffer));
+    }
+
+    fn bind_framebuffer(&self, framebuffer: u32) {
+        self.framebuffer.set(framebuffer);
+        self.record(Call::BindFramebuffer(framebuffer));
+    }
+
+    fn framebuffer_binding(&self) -> u32 {
+        self.framebuffer.get()
+    }
+
+    fn create_depth_buffer(&self, width: u32, height: u32) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.record(Call::CreateDepthBuffer { id, width, height });
+        id
+    }
+
+    fn delete_depth_buffer(&self, depth_buffer: u32) {
+        self.record(Call::DeleteDepthBuffer(depth_buffer));
+    }
+
+    fn attach_to_framebuffer(&self, texture: u32, depth_buffer: u32) -> Result<(), u32> {
+        self.record(Call::AttachToFramebuffer {
+            texture,
+            depth_buffer,
+        });
+        Ok(())
+    }
+
+    fn create_color_buffer(&self, width: u32, height: u32) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.record(Call::CreateColorBuffer { id, width, height });
+        id
+    }
+
+    fn delete_color_buffer(&self, color_buffer: u32) {
+        self.record(Call::DeleteColorBuffer(color_buffer));
+    }
+
+    fn attach_buffers_to_framebuffer(
+        &self,
+        color_buffer: u32,
+        depth_buffer: u32,
+    ) -> Result<(), u32> {
+        self.record(Call::AttachBuffersToFramebuffer {
+            color_buffer,
+            depth_buffer,
+        });
+        Ok(())
+    }
+
+    fn create_program(&self, _: &str, _: &str) -> Result<u32, String> {
+        Ok(self.create(Call::CreateProgram))
+    }
+
+    fn delete_program(&self, program: u32) {
+        self.record(Call::DeleteProgram(program));
+    }
+
+    fn use_program(&self, program: u32) {
+        self.record(Call::UseProgram(program));
+    }
+
+    fn uniform(&self, program: u32, name: &str, value: Uniform) {
+        self.record(Call::Uniform {
+            program,
+            name: name.to_string(),
+            value,
+        });
+    }
+
+    fn draw_arrays(&self, vertices: usize) {
+        self.record(Call::DrawArrays(vertices));
+    }
+
+    fn draw_elements(&self, elements: usize) {
+        self.record(Call::DrawElements(elements));
+    }
+
+    fn draw_elements_base_vertex(&self, elements: usize, offset: usize, base_vertex: usize) {
+        self.record(Call::DrawElementsBaseVertex {
+            elements,
+            offset,
+            base_vertex,
+        });
+    }
+
+    fn setup(&self) {
+        self.record(Call::Setup);
+    }
+
+    fn set_viewport(&self, width: i32, height: i32, _: [f32; 4]) {
+        self.record(Call::SetViewport(width, height));
+    }
+
+    fn clear(&self) {
+        self.record(Call::Clear);
+    }
+
+    fn read_depth(&self, x: i32, y: i32) -> f32 {
+        self.record(Call::ReadDepth(x, y));
+        self.depth.get()
+    }
+
+    fn read_pixels(&self, width: u32, height: u32) -> RgbaImage {
+        self.record(Call::ReadPixels(width, height));
+        RgbaImage::new(width, height)
+    }
+
+    fn read_pixels_to_buffer(&self, width: u32, height: u32) {
+        self.record(Call::ReadPixelsToBuffer(width, height));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_flip_rows() {
+        let pixels = vec![1, 2, 3, 4, 5, 6];
+        assert_eq!(flip_rows(pixels, 2), vec![5, 6, 3, 4, 1, 2]);
+    }
+}
@@ -1,22 +1,22 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
 use super::super::texture::Texture;
 use super::super::vertex_objects::VBO;
-use super::Drawing;
+use super::{Drawing, DrawingDescription, TextureHandle};
 use coords::WorldCoord;
+use image::RgbaImage;
 use std::sync::Arc;
 
 pub struct Billboard {
     vbo: VBO,
-    texture: Arc<Texture>,
+    texture: Texture,
 }
 
 impl Drawing for Billboard {
-    fn draw(&self) {
-        unsafe {
-            self.texture.bind();
-            self.vbo.draw();
-            self.texture.unbind();
-        }
+    fn draw(&self, _: &ViewFrustum) {
+        self.texture.bind();
+        self.vbo.draw();
+        self.texture.unbind();
     }
 
     fn get_z_mod(&self) -> f32 {
@@ -30,13 +30,29 @@ impl Drawing for Billboard {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        None
+    }
 }
 
 impl Billboard {
-    #[rustfmt::skip]
-    pub fn new(world_coord: WorldCoord, width: f32, height: f32, texture: Arc<Texture>) -> Billboard {
+    pub fn new(
+        world_coord: WorldCoord,
+        width: f32,
+        height: f32,
+        image: Arc<RgbaImage>,
+    ) -> Billboard {
+        let texture = Texture::new(&image);
+        let description = Billboard::describe(world_coord, width, height, image);
         let mut vbo = VBO::new(DrawingType::Billboard);
+        vbo.load(description.vertices);
 
+        Billboard { vbo, texture }
+    }
+
+    #[rustfmt::skip]
+    pub fn describe(world_coord: WorldCoord, width: f32, height: f32, image: Arc<RgbaImage>) -> DrawingDescription {
         let p = world_coord;
 
         let left = -width / 2.0;
@@ -53,8 +69,13 @@ impl Billboard {
             p.x, p.y, p.z, 1.0, 1.0, right, top,
         ];
 
-        vbo.load(vertices);
-
-        Billboard{vbo, texture}
+        DrawingDescription {
+            drawing_type: DrawingType::Billboard,
+            vertices,
+            texture: Some(TextureHandle::Image(image)),
+            z_mod: 0.0,
+            visibility_check_coord: None,
+            uniforms: vec![],
+        }
     }
 }
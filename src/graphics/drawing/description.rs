@@ -0,0 +1,165 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
+use super::super::device::Uniform;
+use super::super::engine::DrawingType;
+use super::super::texture::Texture;
+use super::super::vertex_objects::VBO;
+use super::Drawing;
+use coords::WorldCoord;
+use font::Font;
+use image::RgbaImage;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub enum TextureHandle {
+    Image(Arc<RgbaImage>),
+    Font(Arc<Font>),
+}
+
+impl TextureHandle {
+    pub fn image(&self) -> &Arc<RgbaImage> {
+        match self {
+            TextureHandle::Image(image) => image,
+            TextureHandle::Font(font) => font.image(),
+        }
+    }
+}
+
+pub struct DrawingDescription {
+    pub drawing_type: DrawingType,
+    pub vertices: Vec<f32>,
+    pub texture: Option<TextureHandle>,
+    pub z_mod: f32,
+    pub visibility_check_coord: Option<WorldCoord>,
+    pub uniforms: Vec<(String, Uniform)>,
+}
+
+impl DrawingDescription {
+    pub fn plain(vertices: Vec<f32>) -> DrawingDescription {
+        DrawingDescription {
+            drawing_type: DrawingType::Plain,
+            vertices,
+            texture: None,
+            z_mod: 0.0,
+            visibility_check_coord: None,
+            uniforms: vec![],
+        }
+    }
+
+    pub fn with_z_mod(mut self, z_mod: f32) -> DrawingDescription {
+        self.z_mod = z_mod;
+        self
+    }
+
+    pub fn with_uniform(mut self, name: &str, value: Uniform) -> DrawingDescription {
+        self.uniforms.push((name.to_string(), value));
+        self
+    }
+
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        match self.drawing_type {
+            DrawingType::Plain => {
+                BoundingBox::from_vertices(&self.vertices, self.drawing_type.floats_per_vertex())
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct DescribedDrawing {
+    vbo: VBO,
+    texture: Option<Rc<Texture>>,
+    z_mod: f32,
+    visibility_check_coord: Option<WorldCoord>,
+    bounds: Option<BoundingBox>,
+    uniforms: Vec<(String, Uniform)>,
+}
+
+impl Drawing for DescribedDrawing {
+    fn draw(&self, _: &ViewFrustum) {
+        match &self.texture {
+            Some(texture) => {
+                texture.bind();
+                self.vbo.draw();
+                texture.unbind();
+            }
+            None => self.vbo.draw(),
+        }
+    }
+
+    fn get_z_mod(&self) -> f32 {
+        self.z_mod
+    }
+
+    fn drawing_type(&self) -> &DrawingType {
+        self.vbo.drawing_type()
+    }
+
+    fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
+        self.visibility_check_coord.as_ref()
+    }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
+
+    fn uniforms(&self) -> &[(String, Uniform)] {
+        &self.uniforms
+    }
+}
+
+impl DescribedDrawing {
+    pub fn new(description: DrawingDescription, texture: Option<Rc<Texture>>) -> DescribedDrawing {
+        let bounds = description.bounds();
+        let mut vbo = VBO::new(description.drawing_type);
+        vbo.load(description.vertices);
+        DescribedDrawing {
+            vbo,
+            texture,
+            z_mod: description.z_mod,
+            visibility_check_coord: description.visibility_check_coord,
+            bounds,
+            uniforms: description.uniforms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_plain_description_bounds() {
+        let description = DrawingDescription::plain(vec![
+            1.0, 2.0, 3.0, 0.0, 0.0, 0.0,
+            -1.0, 4.0, 0.0, 0.0, 0.0, 0.0,
+        ]);
+
+        assert_eq!(
+            description.bounds(),
+            Some(BoundingBox::new(
+                WorldCoord::new(-1.0, 2.0, 0.0),
+                WorldCoord::new(1.0, 4.0, 3.0)
+            ))
+        );
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_billboard_description_has_no_bounds() {
+        let description = DrawingDescription {
+            drawing_type: DrawingType::Billboard,
+            vertices: vec![
+                1.0, 2.0, 3.0, 0.0, 1.0, -1.0, -1.0,
+            ],
+            texture: None,
+            z_mod: 0.0,
+            visibility_check_coord: None,
+            uniforms: vec![],
+        };
+
+        assert_eq!(description.bounds(), None);
+    }
+}
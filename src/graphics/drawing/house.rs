@@ -1,16 +1,18 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
 use super::super::vertex_objects::VBO;
 use super::utils::*;
-use super::Drawing;
+use super::{Drawing, DrawingDescription};
 use color::Color;
 use coords::*;
 
 pub struct HouseDrawing {
     vbo: VBO,
+    bounds: Option<BoundingBox>,
 }
 
 impl Drawing for HouseDrawing {
-    fn draw(&self) {
+    fn draw(&self, _: &ViewFrustum) {
         self.vbo.draw();
     }
 
@@ -25,6 +27,10 @@ impl Drawing for HouseDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
 }
 
 impl HouseDrawing {
@@ -36,6 +42,29 @@ impl HouseDrawing {
         base_color: Color,
         light_direction: na::Vector3<f32>,
     ) -> HouseDrawing {
+        let description = HouseDrawing::describe(
+            world_coordinate,
+            width,
+            height,
+            roof_height,
+            base_color,
+            light_direction,
+        );
+        let bounds = description.bounds();
+        let mut vbo = VBO::new(DrawingType::Plain);
+        vbo.load(description.vertices);
+
+        HouseDrawing { vbo, bounds }
+    }
+
+    pub fn describe(
+        world_coordinate: WorldCoord,
+        width: f32,
+        height: f32,
+        roof_height: f32,
+        base_color: Color,
+        light_direction: na::Vector3<f32>,
+    ) -> DrawingDescription {
         let triangle_coloring: Box<TriangleColoring> =
             Box::new(AngleTriangleColoring::new(base_color, light_direction));
         let square_coloring: Box<SquareColoring> =
@@ -56,8 +85,6 @@ impl HouseDrawing {
 
         let s = na::Vector3::new(x, y, z + height + roof_height);
 
-        let mut vbo = VBO::new(DrawingType::Plain);
-
         let mut vertices = vec![];
         vertices.append(&mut get_colored_vertices_from_square(
             &[e, h, d, a],
@@ -92,8 +119,6 @@ impl HouseDrawing {
             &triangle_coloring,
         ));
 
-        vbo.load(vertices);
-
-        HouseDrawing { vbo }
+        DrawingDescription::plain(vertices)
     }
 }
@@ -1,4 +1,5 @@
 mod billboard;
+mod description;
 mod house;
 mod sea;
 mod selected_cell;
@@ -7,6 +8,7 @@ mod text;
 mod utils;
 
 pub use self::billboard::*;
+pub use self::description::*;
 pub use self::house::*;
 pub use self::sea::*;
 pub use self::selected_cell::*;
@@ -14,12 +16,28 @@ pub use self::terrain::*;
 pub use self::text::*;
 pub use self::utils::*;
 
+pub use super::vertex_objects::BufferError;
+
+use super::culling::{BoundingBox, ViewFrustum};
+use super::device::Uniform;
 use super::engine::DrawingType;
 use coords::WorldCoord;
 
+// Drawings own GPU objects, so commands carry a builder that runs on the render thread
+pub type DrawingBuilder = Box<FnOnce() -> Box<Drawing> + Send>;
+
 pub trait Drawing {
-    fn draw(&self);
+    fn draw(&self, frustum: &ViewFrustum);
     fn get_z_mod(&self) -> f32;
     fn drawing_type(&self) -> &DrawingType;
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord>;
+    fn get_bounds(&self) -> Option<&BoundingBox>;
+
+    fn mesh_stats(&self) -> Option<MeshStats> {
+        None
+    }
+
+    fn uniforms(&self) -> &[(String, Uniform)] {
+        &[]
+    }
 }
@@ -1,17 +1,19 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
 use super::super::vertex_objects::VBO;
 use super::utils::*;
-use super::Drawing;
+use super::{Drawing, DrawingDescription};
 use color::Color;
 use coords::WorldCoord;
 use v3;
 
 pub struct SeaDrawing {
     vbo: VBO,
+    bounds: Option<BoundingBox>,
 }
 
 impl Drawing for SeaDrawing {
-    fn draw(&self) {
+    fn draw(&self, _: &ViewFrustum) {
         self.vbo.draw();
     }
 
@@ -26,19 +28,30 @@ impl Drawing for SeaDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
 }
 
 impl SeaDrawing {
     pub fn new(width: f32, height: f32, level: f32) -> SeaDrawing {
+        let description = SeaDrawing::describe(width, height, level);
+        let bounds = description.bounds();
         let mut vbo = VBO::new(DrawingType::Plain);
+        vbo.load(description.vertices);
 
+        SeaDrawing { vbo, bounds }
+    }
+
+    pub fn describe(width: f32, height: f32, level: f32) -> DrawingDescription {
         let color = Color::new(0.0, 0.0, 1.0, 1.0);
 
         let left = -0.5 * width;
         let right = 1.5 * width;
         let top = -0.5 * height;
         let bottom = 1.5 * height;
-        vbo.load(get_uniform_colored_vertices_from_square(
+        DrawingDescription::plain(get_uniform_colored_vertices_from_square(
             &[
                 v3(left, top, level),
                 v3(right, top, level),
@@ -46,8 +59,6 @@ impl SeaDrawing {
                 v3(left, bottom, level),
             ],
             &color,
-        ));
-
-        SeaDrawing { vbo }
+        ))
     }
 }
@@ -1,7 +1,8 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
 use super::super::vertex_objects::VBO;
 use super::utils::*;
-use super::Drawing;
+use super::{Drawing, DrawingDescription};
 use color::Color;
 use coords::*;
 use terrain::Terrain;
@@ -9,10 +10,11 @@ use v2;
 
 pub struct SelectedCellDrawing {
     vbo: VBO,
+    bounds: Option<BoundingBox>,
 }
 
 impl Drawing for SelectedCellDrawing {
-    fn draw(&self) {
+    fn draw(&self, _: &ViewFrustum) {
         self.vbo.draw();
     }
 
@@ -27,6 +29,10 @@ impl Drawing for SelectedCellDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
 }
 
 impl SelectedCellDrawing {
@@ -34,6 +40,16 @@ impl SelectedCellDrawing {
         terrain: &Terrain,
         world_coordinate: WorldCoord,
     ) -> Option<SelectedCellDrawing> {
+        SelectedCellDrawing::describe(terrain, world_coordinate).map(|description| {
+            let bounds = description.bounds();
+            let mut vbo = VBO::new(DrawingType::Plain);
+            vbo.load(description.vertices);
+
+            SelectedCellDrawing { vbo, bounds }
+        })
+    }
+
+    pub fn describe(terrain: &Terrain, world_coordinate: WorldCoord) -> Option<DrawingDescription> {
         let color = Color::new(1.0, 0.0, 0.0, 1.0);
 
         let width = (terrain.width() / 2) as f32;
@@ -56,10 +72,6 @@ impl SelectedCellDrawing {
             ));
         }
 
-        let mut vbo = VBO::new(DrawingType::Plain);
-
-        vbo.load(vertices);
-
-        Some(SelectedCellDrawing { vbo })
+        Some(DrawingDescription::plain(vertices).with_z_mod(-0.0001))
     }
 }
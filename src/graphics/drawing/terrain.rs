@@ -1,20 +1,25 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
-use super::super::vertex_objects::{MultiVBO, VBO};
+use super::super::vertex_objects::{BufferError, IndexedVBO, MultiVBO, VBO};
 use super::utils::*;
-use super::Drawing;
+use super::{Drawing, DrawingDescription};
 use color::Color;
 use coords::WorldCoord;
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 use terrain::{Edge, Node, Terrain};
 use utils::Index2D;
 use {v2, M, V2};
 
 pub struct NodeDrawing {
-    vbo: VBO,
+    vbo: IndexedVBO,
     z_mod: f32,
+    bounds: Option<BoundingBox>,
 }
 
 impl Drawing for NodeDrawing {
-    fn draw(&self) {
+    fn draw(&self, _: &ViewFrustum) {
         self.vbo.draw();
     }
 
@@ -29,12 +34,32 @@ impl Drawing for NodeDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
 }
 
 impl NodeDrawing {
     pub fn new(terrain: &Terrain, nodes: &Vec<Node>, color: &Color, z_mod: f32) -> NodeDrawing {
-        let mut vbo = VBO::new(DrawingType::Plain);
+        let description = NodeDrawing::describe(terrain, nodes, color, z_mod);
+        let bounds = description.bounds();
+        let mesh = IndexedMesh::from_vertices(
+            &description.vertices,
+            DrawingType::Plain.floats_per_vertex(),
+        );
+        let mut vbo = IndexedVBO::new(DrawingType::Plain);
+        vbo.load(mesh.vertices, mesh.indices);
+
+        NodeDrawing { vbo, z_mod, bounds }
+    }
 
+    pub fn describe(
+        terrain: &Terrain,
+        nodes: &Vec<Node>,
+        color: &Color,
+        z_mod: f32,
+    ) -> DrawingDescription {
         let mut vertices = vec![];
 
         for node in nodes {
@@ -45,19 +70,18 @@ impl NodeDrawing {
             }
         }
 
-        vbo.load(vertices);
-
-        NodeDrawing { vbo, z_mod }
+        DrawingDescription::plain(vertices).with_z_mod(z_mod)
     }
 }
 
 pub struct EdgeDrawing {
-    vbo: VBO,
+    vbo: IndexedVBO,
     z_mod: f32,
+    bounds: Option<BoundingBox>,
 }
 
 impl Drawing for EdgeDrawing {
-    fn draw(&self) {
+    fn draw(&self, _: &ViewFrustum) {
         self.vbo.draw();
     }
 
@@ -72,12 +96,32 @@ impl Drawing for EdgeDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        self.bounds.as_ref()
+    }
 }
 
 impl EdgeDrawing {
     pub fn new(terrain: &Terrain, nodes: &Vec<Edge>, color: &Color, z_mod: f32) -> EdgeDrawing {
-        let mut vbo = VBO::new(DrawingType::Plain);
+        let description = EdgeDrawing::describe(terrain, nodes, color, z_mod);
+        let bounds = description.bounds();
+        let mesh = IndexedMesh::from_vertices(
+            &description.vertices,
+            DrawingType::Plain.floats_per_vertex(),
+        );
+        let mut vbo = IndexedVBO::new(DrawingType::Plain);
+        vbo.load(mesh.vertices, mesh.indices);
+
+        EdgeDrawing { vbo, z_mod, bounds }
+    }
 
+    pub fn describe(
+        terrain: &Terrain,
+        nodes: &Vec<Edge>,
+        color: &Color,
+        z_mod: f32,
+    ) -> DrawingDescription {
         let mut vertices = vec![];
 
         for node in nodes {
@@ -88,9 +132,7 @@ impl EdgeDrawing {
             }
         }
 
-        vbo.load(vertices);
-
-        EdgeDrawing { vbo, z_mod }
+        DrawingDescription::plain(vertices).with_z_mod(z_mod)
     }
 }
 
@@ -106,6 +148,15 @@ struct TerrainIndexOutOfBounds {
     index: TerrainIndex,
 }
 
+impl From<TerrainIndexOutOfBounds> for BufferError {
+    fn from(error: TerrainIndexOutOfBounds) -> BufferError {
+        BufferError::SlabOutOfBounds {
+            x: error.slab.x,
+            y: error.slab.y,
+        }
+    }
+}
+
 impl TerrainIndex {
     pub fn new(width: usize, height: usize, slab_size: usize) -> TerrainIndex {
         TerrainIndex {
@@ -125,17 +176,147 @@ impl TerrainIndex {
     pub fn indices(&self) -> usize {
         self.index.indices()
     }
+
+    pub fn slabs(&self) -> Vec<V2<usize>> {
+        let mut out = vec![];
+        for y in 0..self.index.rows() {
+            for x in 0..self.index.columns() {
+                out.push(v2(x * self.slab_size, y * self.slab_size));
+            }
+        }
+        out
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SlabGeometry {
+    index: usize,
+    mesh: IndexedMesh,
+    bounds: Option<BoundingBox>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TerrainDescription {
+    pub width: usize,
+    pub height: usize,
+    pub slab_size: usize,
+    pub slabs: Vec<SlabGeometry>,
+    pub time_unindexed_upload: bool,
+}
+
+impl TerrainDescription {
+    pub fn describe(
+        terrain: &Terrain,
+        color_matrix: &M<Color>,
+        shading: &(SquareColoring + Sync),
+        slab_size: usize,
+        progress: &(Fn(usize, usize) + Sync),
+    ) -> TerrainDescription {
+        let (width, height) = color_matrix.shape();
+        let index = TerrainIndex::new(width, height, slab_size);
+        TerrainDescription {
+            width,
+            height,
+            slab_size,
+            slabs: get_all_slab_geometry(index, terrain, color_matrix, shading, progress),
+            time_unindexed_upload: false,
+        }
+    }
+
+    pub fn with_unindexed_upload_timing(mut self) -> TerrainDescription {
+        self.time_unindexed_upload = true;
+        self
+    }
+
+    pub fn vertices(&self) -> Vec<f32> {
+        let floats_per_vertex = DrawingType::Plain.floats_per_vertex();
+        self.slabs
+            .iter()
+            .flat_map(|slab| slab.mesh.unindexed(floats_per_vertex))
+            .collect()
+    }
+}
+
+fn get_slab_geometry(
+    index: usize,
+    terrain: &Terrain,
+    color_matrix: &M<Color>,
+    shading: &SquareColoring,
+    from: V2<usize>,
+    to: V2<usize>,
+) -> SlabGeometry {
+    let mut vertices = vec![];
+
+    for x in from.x..to.x {
+        for y in from.y..to.y {
+            let tile_index = v2(x, y);
+            let grid_index = Terrain::get_index_for_tile(&tile_index);
+            let border = terrain.get_border(grid_index);
+            let shade = shading.get_colors(&[border[0], border[1], border[2], border[3]])[0];
+            let color = color_matrix[(x, y)].mul(&shade);
+            for triangle in terrain.get_triangles_for_tile(&tile_index) {
+                vertices.append(&mut get_uniform_colored_vertices_from_triangle(
+                    &triangle, &color,
+                ));
+            }
+        }
+    }
+
+    let floats_per_vertex = DrawingType::Plain.floats_per_vertex();
+    SlabGeometry {
+        index,
+        bounds: BoundingBox::from_vertices(&vertices, floats_per_vertex),
+        mesh: IndexedMesh::from_vertices(&vertices, floats_per_vertex),
+    }
+}
+
+fn get_all_slab_geometry(
+    index: TerrainIndex,
+    terrain: &Terrain,
+    color_matrix: &M<Color>,
+    shading: &(SquareColoring + Sync),
+    progress: &(Fn(usize, usize) + Sync),
+) -> Vec<SlabGeometry> {
+    let slabs = index.slabs();
+    let total = slabs.len();
+    let done = AtomicUsize::new(0);
+    let (width, height) = color_matrix.shape();
+
+    slabs
+        .into_par_iter()
+        .enumerate()
+        .map(|(slab, from)| {
+            let to = v2(
+                (from.x + index.slab_size).min(width),
+                (from.y + index.slab_size).min(height),
+            );
+            let geometry = get_slab_geometry(slab, terrain, color_matrix, shading, from, to);
+            progress(done.fetch_add(1, Ordering::SeqCst) + 1, total);
+            geometry
+        })
+        .collect()
 }
 
 #[derive(Clone)]
 pub struct TerrainDrawing {
     vbo: MultiVBO,
     index: TerrainIndex,
+    bounds: Vec<Option<BoundingBox>>,
+    stats: Vec<MeshStats>,
+    time_unindexed_upload: bool,
 }
 
 impl Drawing for TerrainDrawing {
-    fn draw(&self) {
-        self.vbo.draw();
+    fn draw(&self, frustum: &ViewFrustum) {
+        let visible: Vec<bool> = self
+            .bounds
+            .iter()
+            .map(|bounds| match bounds {
+                Some(bounds) => frustum.is_visible(bounds),
+                None => false,
+            })
+            .collect();
+        self.vbo.draw(&visible);
     }
 
     fn get_z_mod(&self) -> f32 {
@@ -149,16 +330,67 @@ impl Drawing for TerrainDrawing {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         None
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        None
+    }
+
+    fn mesh_stats(&self) -> Option<MeshStats> {
+        Some(self.stats())
+    }
 }
 
 impl TerrainDrawing {
     pub fn new(width: usize, height: usize, slab_size: usize) -> TerrainDrawing {
-        let max_floats_per_index = 9 * // 9 floats per triangle
-            2 * // 2 triangles per cell
-            slab_size * slab_size * 4; // cells per slab
+        let max_floats_per_index = DrawingType::Plain.floats_per_vertex() * // floats per vertex
+            3 * // vertices per triangle when none are shared
+            4 * // 4 triangles per tile
+            slab_size * slab_size; // tiles per slab
+        let max_elements_per_index = 3 * // 3 elements per triangle
+            4 * // 4 triangles per tile
+            slab_size * slab_size; // tiles per slab
         let index = TerrainIndex::new(width, height, slab_size);
-        let vbo = MultiVBO::new(DrawingType::Plain, index.indices(), max_floats_per_index);
-        TerrainDrawing { vbo, index }
+        let vbo = MultiVBO::new(
+            DrawingType::Plain,
+            index.indices(),
+            max_floats_per_index,
+            max_elements_per_index,
+        );
+        TerrainDrawing {
+            vbo,
+            index,
+            bounds: vec![None; index.indices()],
+            stats: vec![MeshStats::default(); index.indices()],
+            time_unindexed_upload: false,
+        }
+    }
+
+    pub fn time_unindexed_upload(&mut self, time_unindexed_upload: bool) {
+        self.time_unindexed_upload = time_unindexed_upload;
+    }
+
+    pub fn from_description(
+        description: TerrainDescription,
+    ) -> Result<TerrainDrawing, BufferError> {
+        let mut out =
+            TerrainDrawing::new(description.width, description.height, description.slab_size);
+        out.time_unindexed_upload(description.time_unindexed_upload);
+        out.upload_all(description.slabs, &|_, _| ())?;
+        Ok(out)
+    }
+
+    pub fn compact(&mut self) {
+        self.vbo.compact();
+    }
+
+    pub fn free_bytes(&self) -> usize {
+        self.vbo.free_bytes()
+    }
+
+    pub fn stats(&self) -> MeshStats {
+        self.stats
+            .iter()
+            .fold(MeshStats::default(), |total, stats| total + *stats)
     }
 
     pub fn update(
@@ -168,33 +400,170 @@ impl TerrainDrawing {
         shading: &Box<SquareColoring>,
         from: V2<usize>,
         to: V2<usize>,
-    ) {
-        let mut vertices = vec![];
+    ) -> Result<(), BufferError> {
+        let geometry = self.get_geometry(terrain, color_matrix, shading.as_ref(), from, to)?;
+        self.upload(geometry)
+    }
 
-        for x in from.x..to.x {
-            for y in from.y..to.y {
-                let tile_index = v2(x, y);
-                let grid_index = Terrain::get_index_for_tile(&tile_index);
-                let border = terrain.get_border(grid_index);
-                let shade = shading.get_colors(&[border[0], border[1], border[2], border[3]])[0];
-                let color = color_matrix[(x, y)].mul(&shade);
-                for triangle in terrain.get_triangles_for_tile(&tile_index) {
-                    vertices.append(&mut get_uniform_colored_vertices_from_triangle(
-                        &triangle, &color,
-                    ));
-                }
-            }
+    pub fn get_geometry(
+        &self,
+        terrain: &Terrain,
+        color_matrix: &M<Color>,
+        shading: &SquareColoring,
+        from: V2<usize>,
+        to: V2<usize>,
+    ) -> Result<SlabGeometry, BufferError> {
+        let index = self.index.get(from)?;
+        Ok(get_slab_geometry(
+            index,
+            terrain,
+            color_matrix,
+            shading,
+            from,
+            to,
+        ))
+    }
+
+    pub fn get_all_geometry(
+        &self,
+        terrain: &Terrain,
+        color_matrix: &M<Color>,
+        shading: &(SquareColoring + Sync),
+        progress: &(Fn(usize, usize) + Sync),
+    ) -> Vec<SlabGeometry> {
+        get_all_slab_geometry(self.index, terrain, color_matrix, shading, progress)
+    }
+
+    pub fn upload(&mut self, geometry: SlabGeometry) -> Result<(), BufferError> {
+        let SlabGeometry {
+            index,
+            mesh,
+            bounds,
+        } = geometry;
+        let floats_per_vertex = DrawingType::Plain.floats_per_vertex();
+        let mut stats = mesh.stats(floats_per_vertex);
+        if self.time_unindexed_upload {
+            let floats = mesh.unindexed(floats_per_vertex);
+            let mut vbo = VBO::new(DrawingType::Plain);
+            let start = Instant::now();
+            vbo.load(floats);
+            stats.unindexed_upload_time = Some(start.elapsed());
         }
+        let start = Instant::now();
+        self.vbo.load(index, mesh.vertices, mesh.indices)?;
+        stats.upload_time = start.elapsed();
+        self.stats[index] = stats;
+        self.bounds[index] = bounds;
+        Ok(())
+    }
 
-        let index = self.index.get(from).unwrap();
-        self.vbo.load(index, vertices);
+    pub fn upload_all(
+        &mut self,
+        geometry: Vec<SlabGeometry>,
+        progress: &Fn(usize, usize),
+    ) -> Result<(), BufferError> {
+        let total = geometry.len();
+        for (i, slab) in geometry.into_iter().enumerate() {
+            self.upload(slab)?;
+            progress(i + 1, total);
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use super::super::super::device::RecordingDevice;
     use super::*;
+    use std::sync::Mutex;
+    use v3;
+
+    #[test]
+    fn test_terrain_index_slabs() {
+        let index = TerrainIndex::new(128, 64, 32);
+        assert_eq!(
+            index.slabs(),
+            vec![
+                v2(0, 0),
+                v2(32, 0),
+                v2(64, 0),
+                v2(96, 0),
+                v2(0, 32),
+                v2(32, 32),
+                v2(64, 32),
+                v2(96, 32),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_slab_geometry_matches_slab_by_slab() {
+        let terrain = Terrain::new(M::from_fn(5, 5, |x, y| (x + y) as f32), &vec![], &vec![]);
+        let color_matrix = M::from_element(4, 4, Color::new(1.0, 0.5, 0.0, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+        let index = TerrainIndex::new(4, 4, 2);
+        let reported = Mutex::new(vec![]);
+
+        let actual =
+            get_all_slab_geometry(index, &terrain, &color_matrix, &shading, &|done, total| {
+                reported.lock().unwrap().push((done, total))
+            });
+
+        let expected: Vec<SlabGeometry> = index
+            .slabs()
+            .into_iter()
+            .map(|from| {
+                get_slab_geometry(
+                    index.get(from).unwrap(),
+                    &terrain,
+                    &color_matrix,
+                    &shading,
+                    from,
+                    from + v2(2, 2),
+                )
+            })
+            .collect();
+        assert_eq!(actual, expected);
+
+        let mut reported = reported.into_inner().unwrap();
+        reported.sort();
+        assert_eq!(reported, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+
+    #[test]
+    fn test_all_slab_geometry_for_non_square_terrain() {
+        let terrain = Terrain::new(M::from_fn(5, 3, |x, y| (x + y) as f32), &vec![], &vec![]);
+        let color_matrix = M::from_element(4, 2, Color::new(1.0, 0.5, 0.0, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+        let index = TerrainIndex::new(4, 2, 2);
+
+        let actual = get_all_slab_geometry(index, &terrain, &color_matrix, &shading, &|_, _| ());
+
+        let expected = vec![
+            get_slab_geometry(0, &terrain, &color_matrix, &shading, v2(0, 0), v2(2, 2)),
+            get_slab_geometry(1, &terrain, &color_matrix, &shading, v2(2, 0), v2(4, 2)),
+        ];
+        assert_eq!(actual, expected);
+        assert!(actual.iter().all(|slab| slab.mesh.indices.len() == 4 * 6));
+    }
+
+    #[test]
+    fn test_terrain_description_vertices_cover_every_tile() {
+        let terrain = Terrain::new(M::from_fn(5, 3, |x, y| (x + y) as f32), &vec![], &vec![]);
+        let color_matrix = M::from_element(4, 2, Color::new(1.0, 0.5, 0.0, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+
+        let description =
+            TerrainDescription::describe(&terrain, &color_matrix, &shading, 2, &|_, _| ());
+
+        assert_eq!((description.width, description.height), (4, 2));
+        assert_eq!(description.slabs.len(), 2);
+        assert_eq!(
+            description.vertices().len(),
+            8 * 6 * DrawingType::Plain.floats_per_vertex()
+        );
+    }
 
     #[test]
     fn test_terrain_index_indices() {
@@ -239,4 +608,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_update_out_of_range_slab_is_error() {
+        RecordingDevice::install();
+        let terrain = Terrain::new(M::zeros(5, 5), &vec![], &vec![]);
+        let color_matrix = M::from_element(4, 4, Color::new(1.0, 0.5, 0.0, 1.0));
+        let shading: Box<SquareColoring> = Box::new(AngleSquareColoring::new(
+            Color::new(1.0, 1.0, 1.0, 1.0),
+            v3(1.0, 0.0, 1.0),
+        ));
+        let mut drawing = TerrainDrawing::new(4, 4, 2);
+
+        assert_eq!(
+            drawing.update(&terrain, &color_matrix, &shading, v2(4, 0), v2(6, 2)),
+            Err(BufferError::SlabOutOfBounds { x: 4, y: 0 })
+        );
+    }
 }
@@ -1,23 +1,23 @@
+use super::super::culling::{BoundingBox, ViewFrustum};
 use super::super::engine::DrawingType;
+use super::super::texture::Texture;
 use super::super::vertex_objects::VBO;
-use super::Drawing;
+use super::{Drawing, DrawingDescription, TextureHandle};
 use coords::WorldCoord;
 use font::Font;
 use std::sync::Arc;
 
 pub struct Text {
     vbo: VBO,
-    font: Arc<Font>,
+    texture: Texture,
     world_coord: WorldCoord,
 }
 
 impl Drawing for Text {
-    fn draw(&self) {
-        unsafe {
-            self.font.texture().bind();
-            self.vbo.draw();
-            self.font.texture().unbind();
-        }
+    fn draw(&self, _: &ViewFrustum) {
+        self.texture.bind();
+        self.vbo.draw();
+        self.texture.unbind();
     }
 
     fn get_z_mod(&self) -> f32 {
@@ -31,13 +31,28 @@ impl Drawing for Text {
     fn get_visibility_check_coord(&self) -> Option<&WorldCoord> {
         Some(&self.world_coord)
     }
+
+    fn get_bounds(&self) -> Option<&BoundingBox> {
+        None
+    }
 }
 
 impl Text {
-    #[rustfmt::skip]
     pub fn new(text: &str, world_coord: WorldCoord, font: Arc<Font>) -> Text {
+        let texture = Texture::new(font.image());
+        let description = Text::describe(text, world_coord, font);
         let mut vbo = VBO::new(DrawingType::Text);
+        vbo.load(description.vertices);
+
+        Text {
+            vbo,
+            texture,
+            world_coord,
+        }
+    }
 
+    #[rustfmt::skip]
+    pub fn describe(text: &str, world_coord: WorldCoord, font: Arc<Font>) -> DrawingDescription {
         let mut vertices = vec![];
 
         let total_width: f32 = font.get_width(text) as f32;
@@ -61,8 +76,13 @@ impl Text {
             xs += font.get_advance(character) as f32;
         }
 
-        vbo.load(vertices);
-
-        Text{vbo, font, world_coord}
+        DrawingDescription {
+            drawing_type: DrawingType::Text,
+            vertices,
+            texture: Some(TextureHandle::Font(font)),
+            z_mod: 0.0,
+            visibility_check_coord: Some(world_coord),
+            uniforms: vec![],
+        }
     }
 }
@@ -1,5 +1,8 @@
 use color::Color;
+use std::collections::HashMap;
 use std::f32;
+use std::ops::Add;
+use std::time::Duration;
 use utils::float_ordering;
 
 pub trait TriangleColoring {
@@ -145,3 +148,184 @@ pub fn get_colored_vertices_from_square(points: &[na::Vector3<f32>; 4], coloring
         points[1].x, points[1].y, points[1].z, colors[1].r, colors[1].g, colors[1].b,
     ]
 }
+
+#[derive(Debug, PartialEq)]
+pub struct IndexedMesh {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+}
+
+impl IndexedMesh {
+    pub fn from_vertices(vertices: &[f32], floats_per_vertex: usize) -> IndexedMesh {
+        let mut lookup: HashMap<Vec<u32>, u32> = HashMap::new();
+        let mut out = IndexedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        for vertex in vertices.chunks(floats_per_vertex) {
+            let key: Vec<u32> = vertex.iter().map(|float| float.to_bits()).collect();
+            let next = lookup.len() as u32;
+            let index = *lookup.entry(key).or_insert_with(|| {
+                out.vertices.extend_from_slice(vertex);
+                next
+            });
+            out.indices.push(index);
+        }
+        out
+    }
+
+    pub fn unindexed(&self, floats_per_vertex: usize) -> Vec<f32> {
+        self.indices
+            .iter()
+            .flat_map(|index| {
+                let start = *index as usize * floats_per_vertex;
+                self.vertices[start..start + floats_per_vertex]
+                    .iter()
+                    .cloned()
+            })
+            .collect()
+    }
+
+    pub fn stats(&self, floats_per_vertex: usize) -> MeshStats {
+        MeshStats {
+            vertex_bytes: self.vertices.len() * std::mem::size_of::<f32>(),
+            index_bytes: self.indices.len() * std::mem::size_of::<u32>(),
+            unindexed_bytes: self.indices.len() * floats_per_vertex * std::mem::size_of::<f32>(),
+            upload_time: Duration::default(),
+            unindexed_upload_time: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct MeshStats {
+    pub vertex_bytes: usize,
+    pub index_bytes: usize,
+    pub unindexed_bytes: usize,
+    pub upload_time: Duration,
+    pub unindexed_upload_time: Option<Duration>,
+}
+
+impl MeshStats {
+    pub fn indexed_bytes(&self) -> usize {
+        self.vertex_bytes + self.index_bytes
+    }
+
+    pub fn saved_bytes(&self) -> isize {
+        self.unindexed_bytes as isize - self.indexed_bytes() as isize
+    }
+
+    pub fn saved_upload_time(&self) -> Option<Duration> {
+        self.unindexed_upload_time
+            .map(|unindexed| unindexed.checked_sub(self.upload_time).unwrap_or_default())
+    }
+}
+
+impl Add for MeshStats {
+    type Output = MeshStats;
+
+    fn add(self, other: MeshStats) -> MeshStats {
+        MeshStats {
+            vertex_bytes: self.vertex_bytes + other.vertex_bytes,
+            index_bytes: self.index_bytes + other.index_bytes,
+            unindexed_bytes: self.unindexed_bytes + other.unindexed_bytes,
+            upload_time: self.upload_time + other.upload_time,
+            unindexed_upload_time: match (self.unindexed_upload_time, other.unindexed_upload_time) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_indexed_mesh_shares_identical_vertices() {
+        let color = Color::new(1.0, 0.5, 0.0, 1.0);
+        let square = [
+            na::Vector3::new(0.0, 0.0, 1.0),
+            na::Vector3::new(1.0, 0.0, 1.0),
+            na::Vector3::new(1.0, 1.0, 1.0),
+            na::Vector3::new(0.0, 1.0, 1.0),
+        ];
+        let vertices = get_uniform_colored_vertices_from_square(&square, &color);
+
+        let actual = IndexedMesh::from_vertices(&vertices, 6);
+
+        assert_eq!(actual.vertices, vec![
+            0.0, 0.0, 1.0, 1.0, 0.5, 0.0,
+            0.0, 1.0, 1.0, 1.0, 0.5, 0.0,
+            1.0, 1.0, 1.0, 1.0, 0.5, 0.0,
+            1.0, 0.0, 1.0, 1.0, 0.5, 0.0,
+        ]);
+        assert_eq!(actual.indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn test_indexed_mesh_keeps_vertices_with_different_colors() {
+        let points = [
+            na::Vector3::new(0.0, 0.0, 0.0),
+            na::Vector3::new(1.0, 0.0, 0.0),
+            na::Vector3::new(1.0, 1.0, 0.0),
+        ];
+        let mut vertices =
+            get_uniform_colored_vertices_from_triangle(&points, &Color::new(1.0, 0.0, 0.0, 1.0));
+        vertices.append(&mut get_uniform_colored_vertices_from_triangle(
+            &points,
+            &Color::new(0.0, 1.0, 0.0, 1.0),
+        ));
+
+        let actual = IndexedMesh::from_vertices(&vertices, 6);
+
+        assert_eq!(actual.vertices.len(), 36);
+        assert_eq!(actual.indices, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_unindexed_mesh_restores_vertices() {
+        #[rustfmt::skip]
+        let vertices = vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+        ];
+        let mesh = IndexedMesh::from_vertices(&vertices, 6);
+
+        assert_eq!(mesh.unindexed(6), vertices);
+    }
+
+    #[test]
+    fn test_mesh_stats() {
+        let mesh = IndexedMesh {
+            vertices: vec![0.0; 24],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+
+        let actual = mesh.stats(6);
+
+        assert_eq!(actual.vertex_bytes, 96);
+        assert_eq!(actual.index_bytes, 24);
+        assert_eq!(actual.unindexed_bytes, 144);
+        assert_eq!(actual.saved_bytes(), 24);
+        assert_eq!(actual.saved_upload_time(), None);
+    }
+
+    #[test]
+    fn test_mesh_stats_add_upload_times() {
+        let stats = |upload, unindexed| MeshStats {
+            upload_time: Duration::from_millis(upload),
+            unindexed_upload_time: Some(Duration::from_millis(unindexed)),
+            ..MeshStats::default()
+        };
+
+        let actual = stats(2, 5) + stats(3, 4);
+
+        assert_eq!(actual.upload_time, Duration::from_millis(5));
+        assert_eq!(actual.saved_upload_time(), Some(Duration::from_millis(4)));
+    }
+}
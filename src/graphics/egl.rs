@@ -0,0 +1,207 @@
+use super::vertex_objects::BufferError;
+use error::Error;
+use shared_library::dynamic_library::DynamicLibrary;
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+use std::ptr;
+
+pub type EGLDisplay = *mut c_void;
+pub type EGLConfig = *mut c_void;
+pub type EGLContext = *mut c_void;
+pub type EGLSurface = *mut c_void;
+pub type EGLint = i32;
+pub type EGLBoolean = u32;
+pub type EGLenum = u32;
+pub type EGLAttrib = isize;
+
+pub const EGL_FALSE: EGLBoolean = 0;
+pub const EGL_NONE: EGLint = 0x3038;
+pub const EGL_SURFACE_TYPE: EGLint = 0x3033;
+pub const EGL_PBUFFER_BIT: EGLint = 0x0001;
+pub const EGL_RENDERABLE_TYPE: EGLint = 0x3040;
+pub const EGL_OPENGL_BIT: EGLint = 0x0008;
+pub const EGL_OPENGL_API: EGLenum = 0x30A2;
+pub const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+pub const EGL_CONTEXT_MINOR_VERSION: EGLint = 0x30FB;
+pub const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLint = 0x30FD;
+pub const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 0x0001;
+pub const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+
+#[derive(Debug, PartialEq)]
+pub enum HeadlessError {
+    LibraryNotFound(String),
+    SymbolNotFound(&'static str),
+    NoDisplay,
+    Initialize(EGLint),
+    NoConfig,
+    CreateContext(EGLint),
+    MakeCurrent(EGLint),
+    IncompleteFramebuffer(gl::types::GLenum),
+    Save(String),
+    Engine(Error),
+    Buffer(BufferError),
+    RenderTarget(String),
+    Capture(String),
+    Unsupported(&'static str),
+}
+
+impl From<Error> for HeadlessError {
+    fn from(error: Error) -> HeadlessError {
+        HeadlessError::Engine(error)
+    }
+}
+
+#[allow(non_snake_case)]
+struct Egl {
+    _library: DynamicLibrary,
+    GetPlatformDisplay: unsafe extern "C" fn(EGLenum, *mut c_void, *const EGLAttrib) -> EGLDisplay,
+    Initialize: unsafe extern "C" fn(EGLDisplay, *mut EGLint, *mut EGLint) -> EGLBoolean,
+    BindAPI: unsafe extern "C" fn(EGLenum) -> EGLBoolean,
+    ChooseConfig: unsafe extern "C" fn(
+        EGLDisplay,
+        *const EGLint,
+        *mut EGLConfig,
+        EGLint,
+        *mut EGLint,
+    ) -> EGLBoolean,
+    CreateContext:
+        unsafe extern "C" fn(EGLDisplay, EGLConfig, EGLContext, *const EGLint) -> EGLContext,
+    DestroyContext: unsafe extern "C" fn(EGLDisplay, EGLContext) -> EGLBoolean,
+    MakeCurrent: unsafe extern "C" fn(EGLDisplay, EGLSurface, EGLSurface, EGLContext) -> EGLBoolean,
+    GetProcAddress: unsafe extern "C" fn(*const c_char) -> *const c_void,
+    GetError: unsafe extern "C" fn() -> EGLint,
+}
+
+unsafe fn load_symbol<F>(library: &DynamicLibrary, name: &'static str) -> Result<F, HeadlessError> {
+    let symbol: *mut c_void = library
+        .symbol(name)
+        .map_err(|_| HeadlessError::SymbolNotFound(name))?;
+    Ok(mem::transmute_copy(&symbol))
+}
+
+impl Egl {
+    const LIBRARY: &'static str = "libEGL.so.1";
+
+    fn load() -> Result<Egl, HeadlessError> {
+        let library = DynamicLibrary::open(Some(Path::new(Egl::LIBRARY)))
+            .map_err(HeadlessError::LibraryNotFound)?;
+        unsafe {
+            Ok(Egl {
+                GetPlatformDisplay: load_symbol(&library, "eglGetPlatformDisplay")?,
+                Initialize: load_symbol(&library, "eglInitialize")?,
+                BindAPI: load_symbol(&library, "eglBindAPI")?,
+                ChooseConfig: load_symbol(&library, "eglChooseConfig")?,
+                CreateContext: load_symbol(&library, "eglCreateContext")?,
+                DestroyContext: load_symbol(&library, "eglDestroyContext")?,
+                MakeCurrent: load_symbol(&library, "eglMakeCurrent")?,
+                GetProcAddress: load_symbol(&library, "eglGetProcAddress")?,
+                GetError: load_symbol(&library, "eglGetError")?,
+                _library: library,
+            })
+        }
+    }
+}
+
+pub struct HeadlessContext {
+    egl: Egl,
+    display: EGLDisplay,
+    context: EGLContext,
+}
+
+impl HeadlessContext {
+    pub fn new(gl_version: (u8, u8)) -> Result<HeadlessContext, HeadlessError> {
+        let egl = Egl::load()?;
+        unsafe {
+            let display = (egl.GetPlatformDisplay)(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                ptr::null_mut(),
+                ptr::null(),
+            );
+            if display.is_null() {
+                return Err(HeadlessError::NoDisplay);
+            }
+
+            let (mut major, mut minor) = (0, 0);
+            if (egl.Initialize)(display, &mut major, &mut minor) == EGL_FALSE {
+                return Err(HeadlessError::Initialize((egl.GetError)()));
+            }
+            (egl.BindAPI)(EGL_OPENGL_API);
+
+            let config_attributes = [
+                EGL_SURFACE_TYPE,
+                EGL_PBUFFER_BIT,
+                EGL_RENDERABLE_TYPE,
+                EGL_OPENGL_BIT,
+                EGL_NONE,
+            ];
+            let mut config = ptr::null_mut();
+            let mut configs = 0;
+            (egl.ChooseConfig)(
+                display,
+                config_attributes.as_ptr(),
+                &mut config,
+                1,
+                &mut configs,
+            );
+            if configs == 0 {
+                return Err(HeadlessError::NoConfig);
+            }
+
+            let context_attributes = [
+                EGL_CONTEXT_MAJOR_VERSION,
+                gl_version.0 as EGLint,
+                EGL_CONTEXT_MINOR_VERSION,
+                gl_version.1 as EGLint,
+                EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                EGL_NONE,
+            ];
+            let context = (egl.CreateContext)(
+                display,
+                config,
+                ptr::null_mut(),
+                context_attributes.as_ptr(),
+            );
+            if context.is_null() {
+                return Err(HeadlessError::CreateContext((egl.GetError)()));
+            }
+
+            Ok(HeadlessContext {
+                egl,
+                display,
+                context,
+            })
+        }
+    }
+
+    pub unsafe fn make_current(&self) -> Result<(), HeadlessError> {
+        if (self.egl.MakeCurrent)(self.display, ptr::null_mut(), ptr::null_mut(), self.context)
+            == EGL_FALSE
+        {
+            return Err(HeadlessError::MakeCurrent((self.egl.GetError)()));
+        }
+        Ok(())
+    }
+
+    pub fn get_proc_address(&self, symbol: &str) -> *const c_void {
+        let symbol = CString::new(symbol).unwrap();
+        unsafe { (self.egl.GetProcAddress)(symbol.as_ptr()) }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        // The surfaceless display is shared by every context in the process, so it is not terminated
+        unsafe {
+            (self.egl.MakeCurrent)(
+                self.display,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            (self.egl.DestroyContext)(self.display, self.context);
+        }
+    }
+}
@@ -1,46 +1,150 @@
-use super::program::Program;
+use super::program::{Program, ProgramSetup};
 use std::collections::HashMap;
 use std::f32::consts::PI;
-use std::ffi::c_void;
+use std::path::PathBuf;
+use std::rc::Rc;
 
-use super::drawing::Drawing;
+use super::culling::ViewFrustum;
+use super::device::{device, RenderDevice};
+use super::drawing::{
+    BufferError, DescribedDrawing, Drawing, DrawingDescription, MeshStats, TerrainDescription,
+    TerrainDrawing,
+};
+use super::render_target::{RenderPass, RenderTarget};
+use super::shader_watcher::ShaderWatcher;
+use super::texture::TextureCache;
+use color::Color;
 use coords::*;
+use error::Error;
+use image::RgbaImage;
 use transform::{Isometric, Transform};
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug, Clone, Copy)]
 pub enum DrawingType {
     Plain,
     Text,
     Billboard,
+    Custom {
+        name: &'static str,
+        attributes: &'static [usize],
+    },
+}
+
+// WorldDrawn fires between world and ui, so only world drawings are picked
+#[derive(PartialEq, Debug, Clone)]
+pub struct DrawOrder {
+    pub world: Vec<DrawingType>,
+    pub ui: Vec<DrawingType>,
+}
+
+impl DrawOrder {
+    pub fn contains(&self, drawing_type: &DrawingType) -> bool {
+        self.world.contains(drawing_type) || self.ui.contains(drawing_type)
+    }
+}
+
+impl Default for DrawOrder {
+    fn default() -> DrawOrder {
+        DrawOrder {
+            world: vec![DrawingType::Plain],
+            ui: vec![DrawingType::Billboard, DrawingType::Text],
+        }
+    }
+}
+
+impl DrawingType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DrawingType::Plain => "plain",
+            DrawingType::Text => "text",
+            DrawingType::Billboard => "billboard",
+            DrawingType::Custom { name, .. } => name,
+        }
+    }
+
+    pub fn floats_per_vertex(&self) -> usize {
+        self.attributes().iter().sum()
+    }
+
+    pub fn attributes(&self) -> &'static [usize] {
+        match self {
+            DrawingType::Plain => &[3, 3],
+            DrawingType::Text | DrawingType::Billboard => &[3, 2, 2],
+            DrawingType::Custom { attributes, .. } => attributes,
+        }
+    }
+}
+
+pub struct FrameUniforms {
+    pub projection: na::Matrix4<f32>,
+    pub pixel_to_screen: na::Matrix2<f32>,
+    pub world_to_screen: na::Matrix3<f32>,
+}
+
+fn setup_plain(program: &Program, uniforms: &FrameUniforms) {
+    program.load_matrix4("projection", uniforms.projection);
+}
+
+fn setup_text(program: &Program, uniforms: &FrameUniforms) {
+    program.load_matrix4("projection", uniforms.projection);
+    program.load_matrix2("pixel_to_screen", uniforms.pixel_to_screen);
+}
+
+fn setup_billboard(program: &Program, uniforms: &FrameUniforms) {
+    program.load_matrix4("projection", uniforms.projection);
+    program.load_matrix3("world_to_screen", uniforms.world_to_screen);
+}
+
+struct ActiveRenderPass {
+    pass: RenderPass,
+    target: RenderTarget,
+    overlay: Option<Box<Drawing>>,
+    frame: u64,
+    stale: bool,
 }
 
 pub struct GraphicsEngine {
-    programs: [Program; 3],
+    device: Rc<RenderDevice>,
+    programs: Vec<Program>,
+    draw_order: DrawOrder,
     viewport_size: glutin::dpi::PhysicalSize,
     transform: Transform,
     transform_matrix: na::Matrix4<f32>,
+    frustum: ViewFrustum,
     projection: Isometric,
     drawings: HashMap<String, Box<Drawing>>,
+    textures: TextureCache,
+    render_passes: HashMap<String, ActiveRenderPass>,
+    shader_watcher: Option<ShaderWatcher>,
+    clear_color: [f32; 4],
 }
 
 impl GraphicsEngine {
-    pub fn new(z_scale: f32, viewport_size: glutin::dpi::PhysicalSize) -> GraphicsEngine {
-        let programs = [
-            Program::from_shaders(
+    pub const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    pub fn new(
+        z_scale: f32,
+        viewport_size: glutin::dpi::PhysicalSize,
+    ) -> Result<GraphicsEngine, Error> {
+        let programs = vec![
+            Program::new(
                 DrawingType::Plain,
                 include_str!("shaders/plain.vert"),
                 include_str!("shaders/plain.frag"),
-            ),
-            Program::from_shaders(
+                Box::new(setup_plain),
+            )?,
+            Program::new(
                 DrawingType::Text,
                 include_str!("shaders/text.vert"),
                 include_str!("shaders/text.frag"),
-            ),
-            Program::from_shaders(
+                Box::new(setup_text),
+            )?,
+            Program::new(
                 DrawingType::Billboard,
                 include_str!("shaders/billboard.vert"),
                 include_str!("shaders/billboard.frag"),
-            ),
+                Box::new(setup_billboard),
+            )?,
         ];
 
         let projection = Isometric::new(PI / 4.0, PI / 3.0);
@@ -56,38 +160,121 @@ impl GraphicsEngine {
         );
 
         let mut out = GraphicsEngine {
+            device: device(),
             programs,
+            draw_order: DrawOrder::default(),
             viewport_size,
             transform_matrix: transform.compute_transformation_matrix(),
+            frustum: ViewFrustum::new(transform.compute_transformation_matrix()),
             transform,
             projection,
             drawings: HashMap::new(),
+            textures: TextureCache::new(),
+            render_passes: HashMap::new(),
+            shader_watcher: None,
+            clear_color: GraphicsEngine::CLEAR_COLOR,
         };
         out.set_viewport_size(viewport_size);
         out.setup_open_gl();
-        out
+        Ok(out)
     }
 
     fn setup_open_gl(&mut self) {
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::Enable(gl::DEPTH_TEST);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        }
+        self.device.setup();
     }
 
     pub fn get_transform(&mut self) -> &mut Transform {
         &mut self.transform
     }
 
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn get_scale(&self) -> GLCoord3D {
+        self.transform.get_scale()
+    }
+
+    pub fn get_translation(&self) -> GLCoord2D {
+        self.transform.get_translation()
+    }
+
+    pub fn get_projection(&self) -> Isometric {
+        self.projection
+    }
+
+    pub fn get_world_coord(&self, gl_coord: GLCoord4D) -> WorldCoord {
+        gl_coord.to_world_coord(&self.transform)
+    }
+
+    pub fn get_world_coord_at(&self, position: glutin::dpi::PhysicalPosition) -> WorldCoord {
+        self.get_world_coord(position.to_gl_coord_4d(self.viewport_size, &GLZFinder {}))
+    }
+
+    pub fn has_drawing(&self, name: &str) -> bool {
+        self.drawings.contains_key(name)
+    }
+
+    pub fn get_mesh_stats(&self, name: &str) -> Option<MeshStats> {
+        self.drawings.get(name)?.mesh_stats()
+    }
+
+    pub fn get_viewport_size(&self) -> glutin::dpi::PhysicalSize {
+        self.viewport_size
+    }
+
     pub fn add_drawing(&mut self, name: String, drawing: Box<Drawing>) {
         self.drawings.insert(name, drawing);
     }
 
+    pub fn add_description(&mut self, name: String, description: DrawingDescription) {
+        let texture = description
+            .texture
+            .as_ref()
+            .map(|texture| self.textures.get(texture.image()));
+        self.add_drawing(name, Box::new(DescribedDrawing::new(description, texture)));
+    }
+
+    pub fn add_terrain(
+        &mut self,
+        name: String,
+        description: TerrainDescription,
+    ) -> Result<(), BufferError> {
+        let drawing = TerrainDrawing::from_description(description)?;
+        self.add_drawing(name, Box::new(drawing));
+        Ok(())
+    }
+
     pub fn remove_drawing(&mut self, name: &String) {
         self.drawings.remove(name);
     }
 
+    pub fn add_render_pass(&mut self, name: String, pass: RenderPass) -> Result<(), String> {
+        let target = RenderTarget::new(pass.width, pass.height)?;
+        let overlay = pass.overlay.as_ref().map(|overlay| {
+            let drawing: Box<Drawing> = Box::new(DescribedDrawing::new(
+                overlay.describe(),
+                Some(target.texture()),
+            ));
+            drawing
+        });
+        self.render_passes.insert(
+            name,
+            ActiveRenderPass {
+                pass,
+                target,
+                overlay,
+                frame: 0,
+                stale: true,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_render_pass(&mut self, name: &String) {
+        self.render_passes.remove(name);
+    }
+
     fn get_pixel_to_screen(&self) -> na::Matrix2<f32> {
         na::Matrix2::new(
             2.0 / self.viewport_size.width as f32,
@@ -97,29 +284,99 @@ impl GraphicsEngine {
         )
     }
 
-    pub fn prepare_program(&self, program: &Program) {
-        match program.drawing_type {
-            DrawingType::Plain => program.load_matrix4("projection", self.transform_matrix),
-            DrawingType::Text => {
-                program.load_matrix4("projection", self.transform_matrix);
-                program.load_matrix2("pixel_to_screen", self.get_pixel_to_screen());
-            }
-            DrawingType::Billboard => {
-                program.load_matrix4("projection", self.transform_matrix);
-                program.load_matrix3("world_to_screen", self.transform.get_scale_as_matrix());
+    pub fn add_program(
+        &mut self,
+        drawing_type: DrawingType,
+        vertex_shader: &str,
+        fragment_shader: &str,
+        setup: ProgramSetup,
+    ) -> Result<(), Error> {
+        let program = Program::new(drawing_type, vertex_shader, fragment_shader, setup)?;
+        self.programs
+            .retain(|existing| existing.drawing_type != drawing_type);
+        self.programs.push(program);
+        if !self.draw_order.contains(&drawing_type) {
+            self.draw_order.world.push(drawing_type);
+        }
+        Ok(())
+    }
+
+    pub fn watch_shaders(&mut self, directory: PathBuf) {
+        self.shader_watcher = Some(ShaderWatcher::new(directory));
+    }
+
+    pub fn reload_shaders(&mut self) -> Vec<(DrawingType, Result<(), Error>)> {
+        let mut out = vec![];
+        let watcher = match &mut self.shader_watcher {
+            Some(watcher) => watcher,
+            None => return out,
+        };
+        for program in self.programs.iter_mut() {
+            let drawing_type = program.drawing_type;
+            if let Some(sources) = watcher.changed(drawing_type.name()) {
+                let result =
+                    sources
+                        .map_err(Error::Shader)
+                        .and_then(|(vertex_shader, fragment_shader)| {
+                            program.reload(&vertex_shader, &fragment_shader)
+                        });
+                out.push((drawing_type, result));
             }
         }
+        out
+    }
+
+    pub fn get_draw_order(&self) -> &DrawOrder {
+        &self.draw_order
+    }
+
+    pub fn set_draw_order(&mut self, draw_order: DrawOrder) {
+        self.draw_order = draw_order;
     }
 
-    pub fn prepare_program_for_drawing(&self, program: &Program, drawing: &Box<Drawing>) {
+    fn get_program(&self, drawing_type: DrawingType) -> Option<&Program> {
+        self.programs
+            .iter()
+            .find(|program| program.drawing_type == drawing_type)
+    }
+
+    fn frame_uniforms(&self) -> FrameUniforms {
+        FrameUniforms {
+            projection: self.transform_matrix,
+            pixel_to_screen: self.get_pixel_to_screen(),
+            world_to_screen: self.transform.get_scale_as_matrix(),
+        }
+    }
+
+    pub fn prepare_program_for_drawing(&self, program: &Program, drawing: &Drawing) {
         match program.drawing_type {
-            DrawingType::Plain => program.load_float("z_mod", drawing.get_z_mod()),
+            DrawingType::Plain | DrawingType::Custom { .. } => {
+                program.load_float("z_mod", drawing.get_z_mod())
+            }
             _ => (),
         }
+        for (name, value) in drawing.uniforms() {
+            program.load(name, value.clone());
+        }
     }
 
     pub fn update_transform_matrix(&mut self) {
         self.transform_matrix = self.transform.compute_transformation_matrix();
+        self.frustum = ViewFrustum::new(self.transform_matrix);
+    }
+
+    pub fn set_projection(&mut self, projection: Isometric) {
+        self.projection = projection;
+        self.transform.set_projection(Box::new(projection));
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = [color.r, color.g, color.b, color.a];
+        self.device.set_viewport(
+            self.viewport_size.width as i32,
+            self.viewport_size.height as i32,
+            self.clear_color,
+        );
     }
 
     pub fn rotate(&mut self, center: GLCoord4D, yaw: f32) {
@@ -134,32 +391,206 @@ impl GraphicsEngine {
         );
     }
 
-    pub fn draw_world(&mut self) {
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        }
-        self.draw(0);
+    pub fn clear(&self) {
+        self.device.clear();
     }
 
-    pub fn draw_ui(&mut self) {
-        self.draw(1);
+    pub fn draw(&self, drawing_type: DrawingType) {
+        self.draw_drawings(drawing_type, self.drawings.values());
     }
 
-    pub fn draw_billboards(&mut self) {
-        self.draw(2);
+    fn draw_overlays(&self) {
+        for overlay in self
+            .render_passes
+            .values()
+            .filter_map(|active| active.overlay.as_ref())
+        {
+            self.draw_on_screen(overlay.as_ref());
+        }
     }
 
-    fn draw(&mut self, program: usize) {
-        let program = &self.programs[program];
-        self.transform.compute_transformation_matrix();
+    fn draw_drawings<'a, I>(&self, drawing_type: DrawingType, drawings: I)
+    where
+        I: Iterator<Item = &'a Box<Drawing>>,
+    {
+        let program = match self.get_program(drawing_type) {
+            Some(program) => program,
+            None => return,
+        };
         program.set_used();
-        self.prepare_program(program);
-        for drawing in self.drawings.values().filter(|d| self.should_draw(d)) {
+        program.setup(&self.frame_uniforms());
+        for drawing in drawings.filter(|d| self.should_draw(d)) {
             if *drawing.drawing_type() == program.drawing_type {
-                self.prepare_program_for_drawing(program, drawing);
-                drawing.draw();
+                self.prepare_program_for_drawing(program, drawing.as_ref());
+                drawing.draw(&self.frustum);
+            }
+        }
+    }
+
+    pub fn draw_world(&self) {
+        for drawing_type in self.draw_order.world.iter() {
+            self.draw(*drawing_type);
+        }
+    }
+
+    pub fn draw_ui(&self) {
+        for drawing_type in self.draw_order.ui.iter() {
+            self.draw(*drawing_type);
+        }
+        self.draw_overlays();
+    }
+
+    fn draw_on_screen(&self, drawing: &Drawing) {
+        let program = match self.get_program(*drawing.drawing_type()) {
+            Some(program) => program,
+            None => return,
+        };
+        program.set_used();
+        program.setup(&FrameUniforms {
+            projection: na::Matrix4::identity(),
+            ..self.frame_uniforms()
+        });
+        self.prepare_program_for_drawing(program, drawing);
+        drawing.draw(&self.frustum);
+    }
+
+    // A pass is due once a frame has skipped it, so it catches up without forcing every frame
+    pub fn has_due_render_passes(&self) -> bool {
+        self.render_passes.values().any(|active| active.stale)
+    }
+
+    pub fn draw_render_passes(&mut self) {
+        let mut render_passes = std::mem::take(&mut self.render_passes);
+        for active in render_passes.values_mut() {
+            if active.frame % active.pass.every.max(1) as u64 == 0 {
+                self.draw_render_pass(&mut active.pass, &active.target);
+                active.stale = false;
+            } else {
+                active.stale = true;
+            }
+            active.frame += 1;
+        }
+        self.render_passes = render_passes;
+    }
+
+    fn draw_render_pass(&mut self, pass: &mut RenderPass, target: &RenderTarget) {
+        let outline: Option<Box<Drawing>> = pass.viewport_outline.map(|color| {
+            let drawing: Box<Drawing> = Box::new(DescribedDrawing::new(
+                pass.outline(&self.transform, color),
+                None,
+            ));
+            drawing
+        });
+        let previous_framebuffer = self.device.framebuffer_binding();
+        let viewport_size = self.viewport_size;
+        let color = pass.clear_color;
+
+        target.bind();
+        self.device.set_viewport(
+            target.width() as i32,
+            target.height() as i32,
+            [color.r, color.g, color.b, color.a],
+        );
+        self.device.clear();
+        std::mem::swap(&mut self.transform, &mut pass.transform);
+        self.viewport_size =
+            glutin::dpi::PhysicalSize::new(target.width() as f64, target.height() as f64);
+        self.update_transform_matrix();
+
+        let draw_order = self
+            .draw_order
+            .world
+            .iter()
+            .chain(self.draw_order.ui.iter());
+        for drawing_type in draw_order {
+            let drawings = pass
+                .drawings
+                .iter()
+                .filter_map(|name| self.drawings.get(name));
+            self.draw_drawings(*drawing_type, drawings);
+        }
+        if let Some(outline) = outline {
+            self.draw_on_screen(outline.as_ref());
+        }
+
+        std::mem::swap(&mut self.transform, &mut pass.transform);
+        self.viewport_size = viewport_size;
+        self.update_transform_matrix();
+        self.device.bind_framebuffer(previous_framebuffer);
+        self.device.set_viewport(
+            viewport_size.width as i32,
+            viewport_size.height as i32,
+            self.clear_color,
+        );
+    }
+
+    pub fn render(&mut self, include_ui: bool) {
+        self.draw_render_passes();
+        self.draw_frame(include_ui);
+    }
+
+    fn draw_frame(&mut self, include_ui: bool) {
+        self.update_transform_matrix();
+        self.clear();
+        self.draw_world();
+        if include_ui {
+            self.draw_ui();
+        }
+    }
+
+    pub fn read_frame(&self) -> RgbaImage {
+        self.device.read_pixels(
+            self.viewport_size.width as u32,
+            self.viewport_size.height as u32,
+        )
+    }
+
+    pub fn capture(&mut self, include_ui: bool) -> RgbaImage {
+        self.render(include_ui);
+        self.read_frame()
+    }
+
+    pub fn capture_tiled(&mut self, tiles: u32, include_ui: bool) -> Result<RgbaImage, String> {
+        let width = self.viewport_size.width as u32;
+        let height = self.viewport_size.height as u32;
+        if tiles == 0 {
+            return Err("Tiled capture needs at least one tile".to_string());
+        }
+        let too_large = || {
+            format!(
+                "Tiled capture of {}x{} with {} tiles is too large",
+                width, height, tiles
+            )
+        };
+        let tiled_width = width.checked_mul(tiles).ok_or_else(too_large)?;
+        let tiled_height = height.checked_mul(tiles).ok_or_else(too_large)?;
+        (tiled_width as usize)
+            .checked_mul(tiled_height as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .filter(|bytes| *bytes <= isize::MAX as usize)
+            .ok_or_else(too_large)?;
+        let mut out = RgbaImage::new(tiled_width, tiled_height);
+        let origin = GLCoord4D::new(0.0, 0.0, 0.0, 1.0);
+        let scale = tiles as f32;
+
+        self.draw_render_passes();
+        self.transform.scale(origin, GLCoord2D::new(scale, scale));
+        for row in 0..tiles {
+            for column in 0..tiles {
+                let x = scale - 1.0 - 2.0 * column as f32;
+                let y = 2.0 * row as f32 + 1.0 - scale;
+                self.transform.translate(GLCoord2D::new(x, y));
+                self.draw_frame(include_ui);
+                let tile = self.read_frame();
+                image::imageops::replace(&mut out, &tile, column * width, row * height);
+                self.transform.translate(GLCoord2D::new(-x, -y));
             }
         }
+        self.transform
+            .scale(origin, GLCoord2D::new(1.0 / scale, 1.0 / scale));
+        self.update_transform_matrix();
+
+        Ok(out)
     }
 
     pub fn set_viewport_size(&mut self, viewport_size: glutin::dpi::PhysicalSize) {
@@ -171,18 +602,22 @@ impl GraphicsEngine {
             ),
         );
         self.viewport_size = viewport_size;
-        unsafe {
-            gl::Viewport(
-                0,
-                0,
-                viewport_size.width as i32,
-                viewport_size.height as i32,
-            );
-            gl::ClearColor(0.0, 0.0, 1.0, 1.0);
-        }
+        self.device.set_viewport(
+            viewport_size.width as i32,
+            viewport_size.height as i32,
+            self.clear_color,
+        );
     }
 
     fn should_draw(&self, drawing: &Box<Drawing>) -> bool {
+        if let Some(bounds) = drawing.get_bounds() {
+            if !self
+                .frustum
+                .is_visible_with_z_mod(bounds, drawing.get_z_mod())
+            {
+                return false;
+            }
+        }
         match drawing.get_visibility_check_coord() {
             Some(world_coord) => self.is_visible(world_coord),
             None => true,
@@ -205,18 +640,353 @@ pub struct GLZFinder {}
 
 impl ZFinder for GLZFinder {
     fn get_z_at(&self, buffer_coordinate: BufferCoordinate) -> f32 {
-        let mut buffer: Vec<f32> = vec![0.0];
-        unsafe {
-            gl::ReadPixels(
-                buffer_coordinate.x,
-                buffer_coordinate.y,
-                1,
-                1,
-                gl::DEPTH_COMPONENT,
-                gl::FLOAT,
-                buffer.as_mut_ptr() as *mut c_void,
+        2.0 * device().read_depth(buffer_coordinate.x, buffer_coordinate.y) - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice, RenderDevice, Uniform};
+    use super::super::drawing::{DescribedDrawing, DrawingDescription};
+    use super::*;
+
+    #[rustfmt::skip]
+    fn triangle() -> Vec<f32> {
+        vec![
+            0.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 1.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 1.0, 0.0, 0.0,
+        ]
+    }
+
+    #[test]
+    fn test_draw_world_uploads_uniforms_and_draws_plain_drawings() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.add_drawing(
+            "triangle".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription::plain(triangle()).with_z_mod(0.5),
+                None,
+            )),
+        );
+        device.clear_calls();
+
+        engine.update_transform_matrix();
+        engine.clear();
+        engine.draw(DrawingType::Plain);
+
+        let plain = 1; // The plain program is the first object created on the device
+        assert_eq!(device.calls()[0], Call::Clear);
+        assert_eq!(device.calls()[1], Call::UseProgram(plain));
+        assert_eq!(
+            device.uniforms(),
+            vec![
+                (
+                    plain,
+                    "projection".to_string(),
+                    Uniform::Matrix4(engine.transform_matrix)
+                ),
+                (plain, "z_mod".to_string(), Uniform::Float(0.5)),
+            ]
+        );
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(3)]);
+    }
+
+    #[test]
+    fn test_draw_ui_skips_plain_drawings() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.add_drawing(
+            "triangle".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription::plain(triangle()),
+                None,
+            )),
+        );
+        device.clear_calls();
+
+        engine.draw(DrawingType::Text);
+
+        assert_eq!(device.draw_calls(), vec![]);
+    }
+
+    #[test]
+    fn test_capture_tiled_reads_back_every_tile() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 32.0)).unwrap();
+        engine.update_transform_matrix();
+        let transform_matrix = engine.transform_matrix;
+        device.clear_calls();
+
+        let image = engine.capture_tiled(3, false).unwrap();
+
+        assert_eq!(image.dimensions(), (192, 96));
+        let reads = device
+            .calls()
+            .into_iter()
+            .filter(|call| *call == Call::ReadPixels(64, 32))
+            .count();
+        assert_eq!(reads, 9);
+        assert!(engine
+            .transform_matrix
+            .iter()
+            .zip(transform_matrix.iter())
+            .all(|(actual, expected)| (actual - expected).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_capture_tiled_rejects_zero_and_oversized_tiles() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 32.0)).unwrap();
+        engine.update_transform_matrix();
+        let transform_matrix = engine.transform_matrix;
+        device.clear_calls();
+
+        assert!(engine.capture_tiled(0, false).is_err());
+        assert!(engine.capture_tiled(u32::MAX / 32, false).is_err());
+
+        assert_eq!(device.calls(), vec![]);
+        assert_eq!(engine.transform_matrix, transform_matrix);
+        assert_eq!(
+            engine.capture_tiled(1, false).unwrap().dimensions(),
+            (64, 32)
+        );
+    }
+
+    #[test]
+    fn test_render_pass_draws_chosen_drawings_at_chosen_rate() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        for name in &["chosen", "other"] {
+            engine.add_drawing(
+                name.to_string(),
+                Box::new(DescribedDrawing::new(
+                    DrawingDescription::plain(triangle()),
+                    None,
+                )),
             );
         }
-        2.0 * buffer[0] - 1.0
+        let transform = RenderPass::top_down(
+            WorldCoord::new(-1.0, -1.0, 0.0),
+            WorldCoord::new(1.0, 1.0, 0.0),
+            1.0,
+        );
+        engine
+            .add_render_pass(
+                "pass".to_string(),
+                RenderPass::new(16, 16, transform, vec!["chosen".to_string()]).with_every(2),
+            )
+            .unwrap();
+        device.clear_calls();
+        assert!(engine.has_due_render_passes());
+
+        engine.draw_render_passes();
+
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(3)]);
+        assert_eq!(device.framebuffer_binding(), 0);
+        assert_eq!(device.calls().last(), Some(&Call::SetViewport(64, 64)));
+        assert!(!engine.has_due_render_passes());
+
+        device.clear_calls();
+        engine.draw_render_passes();
+        assert_eq!(device.calls(), vec![]);
+        assert!(engine.has_due_render_passes());
+
+        engine.draw_render_passes();
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(3)]);
+        assert!(!engine.has_due_render_passes());
+    }
+
+    #[test]
+    fn test_render_pass_every_zero_draws_every_frame() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.add_drawing(
+            "chosen".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription::plain(triangle()),
+                None,
+            )),
+        );
+        let transform = RenderPass::top_down(
+            WorldCoord::new(-1.0, -1.0, 0.0),
+            WorldCoord::new(1.0, 1.0, 0.0),
+            1.0,
+        );
+        let mut pass = RenderPass::new(16, 16, transform, vec!["chosen".to_string()]);
+        pass.every = 0;
+        engine.add_render_pass("pass".to_string(), pass).unwrap();
+        device.clear_calls();
+
+        engine.draw_render_passes();
+        engine.draw_render_passes();
+
+        assert_eq!(
+            device.draw_calls(),
+            vec![Call::DrawArrays(3), Call::DrawArrays(3)]
+        );
+    }
+
+    const TINTED: DrawingType = DrawingType::Custom {
+        name: "tinted",
+        attributes: &[3, 1],
+    };
+
+    fn tint(program: &Program, _: &FrameUniforms) {
+        program.load_float("tint", 0.25);
+    }
+
+    #[test]
+    fn test_custom_program_draws_drawings_of_its_type() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine
+            .add_program(TINTED, "vertex", "fragment", Box::new(tint))
+            .unwrap();
+        engine.add_drawing(
+            "tinted".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription {
+                    drawing_type: TINTED,
+                    vertices: vec![0.0; 12],
+                    texture: None,
+                    z_mod: 0.0,
+                    visibility_check_coord: None,
+                    uniforms: vec![],
+                },
+                None,
+            )),
+        );
+        device.clear_calls();
+
+        engine.render(true);
+
+        let tinted = 4; // Created after the three built in programs
+        assert_eq!(
+            engine.get_draw_order(),
+            &DrawOrder {
+                world: vec![DrawingType::Plain, TINTED],
+                ui: vec![DrawingType::Billboard, DrawingType::Text],
+            }
+        );
+        assert!(device
+            .uniforms()
+            .contains(&(tinted, "tint".to_string(), Uniform::Float(0.25))));
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(3)]);
+    }
+
+    #[test]
+    fn test_add_program_replaces_program_for_drawing_type() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        device.clear_calls();
+
+        engine
+            .add_program(DrawingType::Plain, "vertex", "fragment", Box::new(tint))
+            .unwrap();
+
+        assert_eq!(
+            device.calls(),
+            vec![Call::CreateProgram(4), Call::DeleteProgram(1)]
+        );
+        assert_eq!(engine.get_draw_order(), &DrawOrder::default());
+    }
+
+    #[test]
+    fn test_description_uniforms_are_loaded_before_drawing() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.add_description(
+            "tinted".to_string(),
+            DrawingDescription::plain(vec![]).with_uniform("tint", Uniform::Float(0.5)),
+        );
+        device.clear_calls();
+
+        engine.draw(DrawingType::Plain);
+
+        assert!(device
+            .uniforms()
+            .contains(&(1, "tint".to_string(), Uniform::Float(0.5))));
+    }
+
+    #[test]
+    fn test_draw_order() {
+        let device = RecordingDevice::install();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.set_draw_order(DrawOrder {
+            world: vec![DrawingType::Text],
+            ui: vec![DrawingType::Plain],
+        });
+        device.clear_calls();
+
+        engine.render(true);
+
+        let used: Vec<Call> = device
+            .calls()
+            .into_iter()
+            .filter(|call| matches!(call, Call::UseProgram(..)))
+            .collect();
+        assert_eq!(used, vec![Call::UseProgram(2), Call::UseProgram(1)]);
+    }
+
+    #[test]
+    fn test_default_draw_order_draws_only_plain_before_world_drawn() {
+        let device = RecordingDevice::install();
+        let engine = GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        let used = |device: &RecordingDevice| -> Vec<Call> {
+            device
+                .calls()
+                .into_iter()
+                .filter(|call| matches!(call, Call::UseProgram(..)))
+                .collect()
+        };
+        device.clear_calls();
+
+        engine.draw_world();
+        assert_eq!(used(&device), vec![Call::UseProgram(1)]);
+        device.clear_calls();
+
+        engine.draw_ui();
+        assert_eq!(
+            used(&device),
+            vec![Call::UseProgram(3), Call::UseProgram(2)]
+        );
+    }
+
+    #[test]
+    fn test_reload_shaders_replaces_changed_programs() {
+        let device = RecordingDevice::install();
+        let directory = ::std::env::temp_dir().join(format!(
+            "isometric-reload-{:?}",
+            ::std::thread::current().id()
+        ));
+        ::std::fs::create_dir_all(&directory).unwrap();
+        ::std::fs::write(directory.join("text.vert"), "vertex").unwrap();
+        ::std::fs::write(directory.join("text.frag"), "fragment").unwrap();
+        let mut engine =
+            GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 64.0)).unwrap();
+        engine.watch_shaders(directory.clone());
+        device.clear_calls();
+
+        assert_eq!(engine.reload_shaders(), vec![(DrawingType::Text, Ok(()))]);
+        assert_eq!(engine.reload_shaders(), vec![]);
+
+        assert_eq!(
+            device.calls(),
+            vec![Call::CreateProgram(4), Call::DeleteProgram(2)]
+        );
+        ::std::fs::remove_dir_all(&directory).unwrap();
     }
 }
@@ -0,0 +1,82 @@
+use super::device::{device, RenderDevice};
+use super::egl::HeadlessError;
+use std::rc::Rc;
+
+pub struct Framebuffer {
+    device: Rc<RenderDevice>,
+    id: u32,
+    color: u32,
+    depth: u32,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Result<Framebuffer, HeadlessError> {
+        let device = device();
+        let out = Framebuffer {
+            id: device.create_framebuffer(),
+            color: device.create_color_buffer(width, height),
+            depth: device.create_depth_buffer(width, height),
+            device,
+        };
+        out.bind();
+        out.device
+            .attach_buffers_to_framebuffer(out.color, out.depth)
+            .map_err(HeadlessError::IncompleteFramebuffer)?;
+        Ok(out)
+    }
+
+    pub fn bind(&self) {
+        self.device.bind_framebuffer(self.id);
+    }
+
+    pub fn unbind(&self) {
+        self.device.bind_framebuffer(0);
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        self.device.delete_framebuffer(self.id);
+        self.device.delete_color_buffer(self.color);
+        self.device.delete_depth_buffer(self.depth);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice};
+    use super::*;
+
+    #[test]
+    fn test_framebuffer_is_created_and_deleted_on_its_device() {
+        let device = RecordingDevice::install();
+
+        drop(Framebuffer::new(8, 4).unwrap());
+
+        assert_eq!(
+            device.calls(),
+            vec![
+                Call::CreateFramebuffer(1),
+                Call::CreateColorBuffer {
+                    id: 2,
+                    width: 8,
+                    height: 4
+                },
+                Call::CreateDepthBuffer {
+                    id: 3,
+                    width: 8,
+                    height: 4
+                },
+                Call::BindFramebuffer(1),
+                Call::AttachBuffersToFramebuffer {
+                    color_buffer: 2,
+                    depth_buffer: 3
+                },
+                Call::DeleteFramebuffer(1),
+                Call::DeleteColorBuffer(2),
+                Call::DeleteDepthBuffer(3),
+            ]
+        );
+    }
+}
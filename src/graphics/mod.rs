@@ -1,6 +1,13 @@
+pub mod culling;
+pub mod device;
 pub mod drawing;
+pub mod egl;
 pub mod engine;
-mod program;
-mod shader;
+pub mod framebuffer;
+pub mod program;
+pub mod rasterizer;
+pub mod recorder;
+pub mod render_target;
+pub(crate) mod shader_watcher;
 pub mod texture;
 mod vertex_objects;
@@ -0,0 +1,371 @@
+use super::engine::DrawingType;
+use coords::{BufferCoordinate, ZFinder};
+use image::{Rgba, RgbaImage};
+
+pub struct Uniforms {
+    pub projection: na::Matrix4<f32>,
+    pub z_mod: f32,
+    pub pixel_to_screen: na::Matrix2<f32>,
+    pub world_to_screen: na::Matrix3<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ShadedVertex {
+    x: f32,
+    y: f32,
+    depth: f32,
+    varying: [f32; 4],
+}
+
+fn shade_vertex(
+    drawing_type: DrawingType,
+    vertex: &[f32],
+    uniforms: &Uniforms,
+) -> na::Vector4<f32> {
+    let mut position = uniforms.projection * na::Vector4::new(vertex[0], vertex[1], vertex[2], 1.0);
+    match drawing_type {
+        DrawingType::Plain => position.z += uniforms.z_mod,
+        DrawingType::Text => {
+            let offset = uniforms.pixel_to_screen * na::Vector2::new(vertex[5], vertex[6]);
+            position.x += offset.x;
+            position.y += offset.y;
+            position.z = -1.0;
+        }
+        DrawingType::Billboard => {
+            let offset =
+                uniforms.world_to_screen * na::Vector3::new(vertex[5], vertex[6], -vertex[6]);
+            position.x += offset.x;
+            position.y += offset.y;
+            position.z += offset.z;
+        }
+        DrawingType::Custom { .. } => (),
+    }
+    position
+}
+
+fn varying(drawing_type: DrawingType, vertex: &[f32]) -> [f32; 4] {
+    match drawing_type {
+        DrawingType::Plain => [vertex[3], vertex[4], vertex[5], 1.0],
+        _ => [vertex[3], vertex[4], 0.0, 0.0],
+    }
+}
+
+fn sample(texture: &RgbaImage, u: f32, v: f32) -> [f32; 4] {
+    let clamp = |t: f32, size: u32| ((t * size as f32).floor().max(0.0) as u32).min(size - 1);
+    let texel = texture.get_pixel(clamp(u, texture.width()), clamp(v, texture.height()));
+    [
+        texel[0] as f32 / 255.0,
+        texel[1] as f32 / 255.0,
+        texel[2] as f32 / 255.0,
+        texel[3] as f32 / 255.0,
+    ]
+}
+
+fn edge(a: &ShadedVertex, b: &ShadedVertex, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+fn is_top_left(a: &ShadedVertex, b: &ShadedVertex) -> bool {
+    (a.y == b.y && b.x < a.x) || b.y < a.y
+}
+
+fn to_unorm(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub struct Rasterizer {
+    color: RgbaImage,
+    depth: Vec<f32>,
+}
+
+impl Rasterizer {
+    pub fn new(width: u32, height: u32) -> Rasterizer {
+        Rasterizer {
+            color: RgbaImage::new(width, height),
+            depth: vec![1.0; (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.color.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.color.height()
+    }
+
+    pub fn clear(&mut self, color: [f32; 4]) {
+        let color = Rgba([
+            to_unorm(color[0]),
+            to_unorm(color[1]),
+            to_unorm(color[2]),
+            to_unorm(color[3]),
+        ]);
+        for pixel in self.color.pixels_mut() {
+            *pixel = color;
+        }
+        for depth in self.depth.iter_mut() {
+            *depth = 1.0;
+        }
+    }
+
+    pub fn image(&self) -> RgbaImage {
+        image::imageops::flip_vertical(&self.color)
+    }
+
+    fn to_window(&self, position: na::Vector4<f32>, varying: [f32; 4]) -> ShadedVertex {
+        let position = position / position.w;
+        ShadedVertex {
+            x: (position.x + 1.0) / 2.0 * self.width() as f32,
+            y: (position.y + 1.0) / 2.0 * self.height() as f32,
+            depth: (position.z + 1.0) / 2.0,
+            varying,
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        drawing_type: DrawingType,
+        vertices: &[f32],
+        uniforms: &Uniforms,
+        texture: Option<&RgbaImage>,
+    ) -> Result<(), String> {
+        if let DrawingType::Custom { name, .. } = drawing_type {
+            return Err(format!("Cannot rasterize custom drawing type {}", name));
+        }
+        let floats_per_vertex = drawing_type.floats_per_vertex();
+        let shaded: Vec<ShadedVertex> = vertices
+            .chunks(floats_per_vertex)
+            .filter(|vertex| vertex.len() == floats_per_vertex)
+            .map(|vertex| {
+                let position = shade_vertex(drawing_type, vertex, uniforms);
+                self.to_window(position, varying(drawing_type, vertex))
+            })
+            .collect();
+        for triangle in shaded.chunks(3) {
+            if triangle.len() == 3 {
+                self.draw_triangle(drawing_type, triangle, texture);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_triangle(
+        &mut self,
+        drawing_type: DrawingType,
+        triangle: &[ShadedVertex],
+        texture: Option<&RgbaImage>,
+    ) {
+        let (a, mut b, mut c) = (&triangle[0], &triangle[1], &triangle[2]);
+        let mut area = edge(a, b, c.x, c.y);
+        if area == 0.0 {
+            return;
+        }
+        if area < 0.0 {
+            ::std::mem::swap(&mut b, &mut c);
+            area = -area;
+        }
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as u32;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as u32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().max(0.0) as u32).min(self.width());
+        let max_y = (a.y.max(b.y).max(c.y).ceil().max(0.0) as u32).min(self.height());
+
+        let edges = [(b, c), (c, a), (a, b)];
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let weights: Vec<f32> = edges.iter().map(|(p, q)| edge(p, q, px, py)).collect();
+                let inside = weights
+                    .iter()
+                    .zip(edges.iter())
+                    .all(|(w, (p, q))| *w > 0.0 || (*w == 0.0 && is_top_left(p, q)));
+                if !inside {
+                    continue;
+                }
+                let (wa, wb, wc) = (weights[0] / area, weights[1] / area, weights[2] / area);
+                let depth = wa * a.depth + wb * b.depth + wc * c.depth;
+                let mut varying = [0.0; 4];
+                for (i, value) in varying.iter_mut().enumerate() {
+                    *value = wa * a.varying[i] + wb * b.varying[i] + wc * c.varying[i];
+                }
+                self.shade_fragment(drawing_type, x, y, depth, varying, texture);
+            }
+        }
+    }
+
+    fn shade_fragment(
+        &mut self,
+        drawing_type: DrawingType,
+        x: u32,
+        y: u32,
+        depth: f32,
+        varying: [f32; 4],
+        texture: Option<&RgbaImage>,
+    ) {
+        if !(0.0..=1.0).contains(&depth) {
+            return;
+        }
+        let index = (y * self.width() + x) as usize;
+        if depth >= self.depth[index] {
+            return;
+        }
+        let source = match drawing_type {
+            DrawingType::Plain => varying,
+            _ => match texture {
+                Some(texture) => sample(texture, varying[0], varying[1]),
+                None => [0.0, 0.0, 0.0, 1.0],
+            },
+        };
+        if drawing_type != DrawingType::Plain && source[3] == 0.0 {
+            return;
+        }
+        self.depth[index] = depth;
+
+        let destination = self.color.get_pixel_mut(x, y);
+        let alpha = source[3];
+        for channel in 0..4 {
+            let value =
+                source[channel] * alpha + (destination[channel] as f32 / 255.0) * (1.0 - alpha);
+            destination[channel] = to_unorm(value);
+        }
+    }
+}
+
+impl ZFinder for Rasterizer {
+    fn get_z_at(&self, buffer_coordinate: BufferCoordinate) -> f32 {
+        let x = buffer_coordinate.x.max(0).min(self.width() as i32 - 1);
+        let y = buffer_coordinate.y.max(0).min(self.height() as i32 - 1);
+        2.0 * self.depth[(y as u32 * self.width() + x as u32) as usize] - 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn uniforms() -> Uniforms {
+        Uniforms {
+            projection: na::Matrix4::identity(),
+            z_mod: 0.0,
+            pixel_to_screen: na::Matrix2::identity(),
+            world_to_screen: na::Matrix3::identity(),
+        }
+    }
+
+    #[rustfmt::skip]
+    fn square(z: f32, color: [f32; 3]) -> Vec<f32> {
+        let [r, g, b] = color;
+        vec![
+            -1.0, -1.0, z, r, g, b,
+            1.0, -1.0, z, r, g, b,
+            1.0, 1.0, z, r, g, b,
+            -1.0, -1.0, z, r, g, b,
+            1.0, 1.0, z, r, g, b,
+            -1.0, 1.0, z, r, g, b,
+        ]
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut rasterizer = Rasterizer::new(2, 2);
+        rasterizer.clear([0.0, 0.0, 1.0, 1.0]);
+        assert!(rasterizer
+            .image()
+            .pixels()
+            .all(|pixel| *pixel == Rgba([0, 0, 255, 255])));
+    }
+
+    #[test]
+    fn test_square_covers_every_pixel_exactly_once() {
+        let mut rasterizer = Rasterizer::new(4, 4);
+        rasterizer.clear([0.0, 0.0, 0.0, 0.0]);
+        rasterizer
+            .draw(
+                DrawingType::Plain,
+                &square(0.0, [1.0, 0.0, 0.0]),
+                &uniforms(),
+                None,
+            )
+            .unwrap();
+        assert!(rasterizer
+            .image()
+            .pixels()
+            .all(|pixel| *pixel == Rgba([255, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_depth_test_keeps_nearest() {
+        let mut rasterizer = Rasterizer::new(2, 2);
+        rasterizer.clear([0.0, 0.0, 0.0, 1.0]);
+        rasterizer
+            .draw(
+                DrawingType::Plain,
+                &square(-0.5, [1.0, 0.0, 0.0]),
+                &uniforms(),
+                None,
+            )
+            .unwrap();
+        rasterizer
+            .draw(
+                DrawingType::Plain,
+                &square(0.5, [0.0, 1.0, 0.0]),
+                &uniforms(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(*rasterizer.image().get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+        assert_eq!(rasterizer.get_z_at(BufferCoordinate { x: 0, y: 0 }), -0.5);
+    }
+
+    #[test]
+    fn test_fragments_outside_depth_range_are_clipped() {
+        let mut rasterizer = Rasterizer::new(2, 2);
+        rasterizer.clear([0.0, 0.0, 0.0, 1.0]);
+        rasterizer
+            .draw(
+                DrawingType::Plain,
+                &square(1.5, [1.0, 0.0, 0.0]),
+                &uniforms(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(*rasterizer.image().get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_billboard_samples_nearest_texel_and_blends() {
+        let mut texture = RgbaImage::new(2, 1);
+        texture.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        texture.put_pixel(1, 0, Rgba([255, 0, 0, 0]));
+        let vertices = vec![
+            0.0, 0.0, 0.0, 0.0, 0.0, -1.0, -1.0,
+            0.0, 0.0, 0.0, 1.0, 0.0, 1.0, -1.0,
+            0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0,
+            0.0, 0.0, 0.0, 0.0, 0.0, -1.0, -1.0,
+            0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0,
+            0.0, 0.0, 0.0, 0.0, 1.0, -1.0, 1.0,
+        ];
+        let mut rasterizer = Rasterizer::new(2, 2);
+        rasterizer.clear([0.0, 0.0, 1.0, 1.0]);
+
+        rasterizer.draw(DrawingType::Billboard, &vertices, &uniforms(), Some(&texture)).unwrap();
+
+        let image = rasterizer.image();
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(1, 0), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_alpha_blending() {
+        let mut rasterizer = Rasterizer::new(1, 1);
+        rasterizer.clear([0.0, 0.0, 1.0, 1.0]);
+        rasterizer.shade_fragment(DrawingType::Plain, 0, 0, 0.5, [1.0, 0.0, 0.0, 0.5], None);
+        assert_eq!(
+            *rasterizer.image().get_pixel(0, 0),
+            Rgba([128, 0, 128, 191])
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use super::device::{device, BufferTarget, RenderDevice};
+use image::RgbaImage;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingOptions {
+    pub directory: PathBuf,
+    pub every: u32,
+    pub frame_rate: Option<f32>,
+}
+
+impl RecordingOptions {
+    pub fn new(directory: PathBuf) -> RecordingOptions {
+        RecordingOptions {
+            directory,
+            every: 1,
+            frame_rate: None,
+        }
+    }
+
+    pub fn with_every(mut self, every: u32) -> RecordingOptions {
+        self.every = every.max(1);
+        self
+    }
+
+    pub fn with_frame_rate(mut self, frame_rate: f32) -> RecordingOptions {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.frame_rate
+            .map(|frame_rate| Duration::from_secs_f64(1.0 / frame_rate as f64))
+    }
+}
+
+struct PixelBuffer {
+    id: u32,
+    size: usize,
+}
+
+struct PendingFrame {
+    buffer: PixelBuffer,
+    index: u32,
+    width: u32,
+    height: u32,
+}
+
+struct EncodedFrame {
+    path: PathBuf,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+pub struct Recorder {
+    device: Rc<RenderDevice>,
+    options: RecordingOptions,
+    started: Instant,
+    frame: u64,
+    saved: u32,
+    pending: VecDeque<PendingFrame>,
+    free: Vec<PixelBuffer>,
+    sender: SyncSender<EncodedFrame>,
+    writer: JoinHandle<Result<(), String>>,
+}
+
+impl Recorder {
+    const FRAMES_IN_FLIGHT: usize = 3;
+
+    pub fn start(options: RecordingOptions) -> Result<Recorder, String> {
+        if let Some(frame_rate) = options.frame_rate {
+            if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+                return Err(format!("Invalid frame rate {}", frame_rate));
+            }
+        }
+        fs::create_dir_all(&options.directory).map_err(|err| err.to_string())?;
+        let (sender, receiver) = sync_channel::<EncodedFrame>(Recorder::FRAMES_IN_FLIGHT);
+        let writer = thread::spawn(move || {
+            let mut out = Ok(());
+            for frame in receiver {
+                let image = RgbaImage::from_raw(frame.width, frame.height, frame.pixels).unwrap();
+                let image = image::imageops::flip_vertical(&image);
+                if let Err(err) = image.save(&frame.path) {
+                    out = out.and(Err(format!("{}: {}", frame.path.display(), err)));
+                }
+            }
+            out
+        });
+        Ok(Recorder {
+            device: device(),
+            options,
+            started: Instant::now(),
+            frame: 0,
+            saved: 0,
+            pending: VecDeque::new(),
+            free: vec![],
+            sender,
+            writer,
+        })
+    }
+
+    pub fn directory(&self) -> &PathBuf {
+        &self.options.directory
+    }
+
+    pub fn frame_path(&self, index: u32) -> PathBuf {
+        self.options.directory.join(format!("{:06}.png", index))
+    }
+
+    fn time(&self) -> Duration {
+        match self.options.frame_rate {
+            Some(frame_rate) => Duration::from_secs_f64(self.frame as f64 / frame_rate as f64),
+            None => self.started.elapsed(),
+        }
+    }
+
+    pub fn capture(&mut self, width: u32, height: u32) -> (u64, Duration) {
+        let frame = self.frame;
+        let time = self.time();
+        if frame % self.options.every as u64 == 0 {
+            if self.pending.len() == Recorder::FRAMES_IN_FLIGHT {
+                self.collect();
+            }
+            self.read(width, height);
+        }
+        self.frame += 1;
+        (frame, time)
+    }
+
+    fn read(&mut self, width: u32, height: u32) {
+        let device = &self.device;
+        let size = (width * height * 4) as usize;
+        let mut buffer = self.free.pop().unwrap_or_else(|| PixelBuffer {
+            id: device.create_buffer(),
+            size: 0,
+        });
+        device.bind_buffer(BufferTarget::PixelPack, buffer.id);
+        if buffer.size != size {
+            device.buffer_data(BufferTarget::PixelPack, size, None);
+            buffer.size = size;
+        }
+        device.read_pixels_to_buffer(width, height);
+        device.bind_buffer(BufferTarget::PixelPack, 0);
+        self.pending.push_back(PendingFrame {
+            buffer,
+            index: self.saved,
+            width,
+            height,
+        });
+        self.saved += 1;
+    }
+
+    fn collect(&mut self) {
+        let pending = match self.pending.pop_front() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let device = &self.device;
+        device.bind_buffer(BufferTarget::PixelPack, pending.buffer.id);
+        let pixels = device.get_buffer_sub_data(BufferTarget::PixelPack, 0, pending.buffer.size);
+        device.bind_buffer(BufferTarget::PixelPack, 0);
+        self.free.push(pending.buffer);
+        let _ = self.sender.send(EncodedFrame {
+            path: self.frame_path(pending.index),
+            width: pending.width,
+            height: pending.height,
+            pixels,
+        });
+    }
+
+    pub fn stop(mut self) -> (u32, Result<(), String>) {
+        while !self.pending.is_empty() {
+            self.collect();
+        }
+        for buffer in self.free.drain(..) {
+            self.device.delete_buffer(buffer.id);
+        }
+        let Recorder {
+            saved,
+            sender,
+            writer,
+            ..
+        } = self;
+        drop(sender);
+        let result = writer
+            .join()
+            .unwrap_or_else(|_| Err("Frame writer panicked".to_string()));
+        (saved, result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice};
+    use super::super::framebuffer::Framebuffer;
+    use super::*;
+    use headless::HeadlessEngine;
+    use image::Rgba;
+    use std::env;
+
+    fn directory(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "isometric-recorder-{}-{:?}",
+            name,
+            thread::current().id()
+        ))
+    }
+
+    #[test]
+    fn test_captures_every_nth_frame() {
+        let device = RecordingDevice::install();
+        let directory = directory("every");
+        let mut recorder =
+            Recorder::start(RecordingOptions::new(directory.clone()).with_every(2)).unwrap();
+
+        for _ in 0..5 {
+            recorder.capture(4, 2);
+        }
+        let (frames, result) = recorder.stop();
+
+        assert_eq!(frames, 3);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            device
+                .calls()
+                .into_iter()
+                .filter(|call| *call == Call::ReadPixelsToBuffer(4, 2))
+                .count(),
+            3
+        );
+        for index in 0..3 {
+            let path = directory.join(format!("{:06}.png", index));
+            assert_eq!(image::open(&path).unwrap().to_rgba().dimensions(), (4, 2));
+        }
+        assert!(!directory.join("000003.png").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_readback_is_deferred_until_buffers_are_in_flight() {
+        let device = RecordingDevice::install();
+        let directory = directory("deferred");
+        let mut recorder = Recorder::start(RecordingOptions::new(directory.clone())).unwrap();
+        let reads = || {
+            device
+                .calls()
+                .into_iter()
+                .filter(|call| matches!(call, Call::GetBufferSubData { .. }))
+                .count()
+        };
+
+        for _ in 0..Recorder::FRAMES_IN_FLIGHT {
+            recorder.capture(4, 2);
+        }
+        assert_eq!(reads(), 0);
+        recorder.capture(4, 2);
+        assert_eq!(reads(), 1);
+
+        assert_eq!(recorder.stop().1, Ok(()));
+        assert_eq!(reads(), Recorder::FRAMES_IN_FLIGHT + 1);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_pixel_buffers_are_reused() {
+        let device = RecordingDevice::install();
+        let directory = directory("reuse");
+        let mut recorder = Recorder::start(RecordingOptions::new(directory.clone())).unwrap();
+        let count = |f: &Fn(&Call) -> bool| device.calls().iter().filter(|call| f(call)).count();
+
+        for _ in 0..10 {
+            recorder.capture(4, 2);
+        }
+        assert_eq!(recorder.stop().1, Ok(()));
+
+        let created = count(&|call| matches!(call, Call::CreateBuffer(..)));
+        assert_eq!(created, Recorder::FRAMES_IN_FLIGHT);
+        assert_eq!(
+            count(&|call| matches!(call, Call::BufferData { .. })),
+            created
+        );
+        assert_eq!(
+            count(&|call| matches!(call, Call::DeleteBuffer(..))),
+            created
+        );
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_frame_rate() {
+        RecordingDevice::install();
+        let directory = directory("invalid");
+
+        assert!(
+            Recorder::start(RecordingOptions::new(directory.clone()).with_frame_rate(0.0)).is_err()
+        );
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn test_virtual_frame_rate() {
+        RecordingDevice::install();
+        let directory = directory("virtual");
+        let mut recorder =
+            Recorder::start(RecordingOptions::new(directory.clone()).with_frame_rate(4.0)).unwrap();
+
+        let times: Vec<(u64, Duration)> = (0..3).map(|_| recorder.capture(1, 1)).collect();
+
+        assert_eq!(
+            times,
+            vec![
+                (0, Duration::from_millis(0)),
+                (1, Duration::from_millis(250)),
+                (2, Duration::from_millis(500)),
+            ]
+        );
+        assert_eq!(recorder.stop().1, Ok(()));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_records_gl_frames() {
+        let _engine = HeadlessEngine::new(8, 4, 100.0).unwrap();
+        let framebuffer = Framebuffer::new(8, 4).unwrap();
+        let directory = directory("gl");
+        let mut recorder = Recorder::start(RecordingOptions::new(directory.clone())).unwrap();
+
+        framebuffer.bind();
+        device().set_viewport(8, 4, [1.0, 0.0, 0.0, 1.0]);
+        device().clear();
+        recorder.capture(8, 4);
+        let (frames, result) = recorder.stop();
+
+        assert_eq!(frames, 1);
+        assert_eq!(result, Ok(()));
+        let image = image::open(directory.join("000000.png")).unwrap().to_rgba();
+        assert!(image.pixels().all(|pixel| *pixel == Rgba([255, 0, 0, 255])));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+use super::device::{device, RenderDevice};
+use super::drawing::DrawingDescription;
+use super::engine::DrawingType;
+use super::texture::Texture;
+use color::Color;
+use coords::*;
+use image::RgbaImage;
+use std::rc::Rc;
+use transform::{Isometric, Transform};
+
+pub struct RenderTarget {
+    device: Rc<RenderDevice>,
+    framebuffer: u32,
+    depth_buffer: u32,
+    texture: Rc<Texture>,
+}
+
+impl RenderTarget {
+    pub fn new(width: u32, height: u32) -> Result<RenderTarget, String> {
+        let device = device();
+        let texture = Texture::new(&RgbaImage::new(width, height));
+        let out = RenderTarget {
+            framebuffer: device.create_framebuffer(),
+            depth_buffer: device.create_depth_buffer(width, height),
+            texture: Rc::new(texture),
+            device: device.clone(),
+        };
+
+        let previous = device.framebuffer_binding();
+        out.bind();
+        let status = device.attach_to_framebuffer(out.texture.id(), out.depth_buffer);
+        device.bind_framebuffer(previous);
+
+        match status {
+            Ok(()) => Ok(out),
+            Err(status) => Err(format!(
+                "Render target {}x{} is incomplete with status {:#x}",
+                width, height, status
+            )),
+        }
+    }
+
+    pub fn texture(&self) -> Rc<Texture> {
+        self.texture.clone()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.height()
+    }
+
+    pub fn bind(&self) {
+        self.device.bind_framebuffer(self.framebuffer);
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        self.device.delete_framebuffer(self.framebuffer);
+        self.device.delete_depth_buffer(self.depth_buffer);
+    }
+}
+
+pub struct Overlay {
+    pub anchor: GLCoord2D,
+    pub left: f32,
+    pub bottom: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Overlay {
+    pub fn bottom_right(width: f32, height: f32, margin: f32) -> Overlay {
+        Overlay {
+            anchor: GLCoord2D::new(1.0, -1.0),
+            left: -margin - width,
+            bottom: margin,
+            width,
+            height,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn describe(&self) -> DrawingDescription {
+        let (x, y) = (self.anchor.x, self.anchor.y);
+        let left = self.left;
+        let right = self.left + self.width;
+        let bottom = self.bottom;
+        let top = self.bottom + self.height;
+
+        let vertices = vec![
+            x, y, 0.0, 0.0, 0.0, left, bottom,
+            x, y, 0.0, 1.0, 0.0, right, bottom,
+            x, y, 0.0, 1.0, 1.0, right, top,
+            x, y, 0.0, 0.0, 0.0, left, bottom,
+            x, y, 0.0, 1.0, 1.0, right, top,
+            x, y, 0.0, 0.0, 1.0, left, top,
+        ];
+
+        DrawingDescription {
+            drawing_type: DrawingType::Text,
+            vertices,
+            texture: None,
+            z_mod: 0.0,
+            visibility_check_coord: None,
+            uniforms: vec![],
+        }
+    }
+}
+
+pub struct RenderPass {
+    pub width: u32,
+    pub height: u32,
+    pub transform: Transform,
+    pub drawings: Vec<String>,
+    pub every: u32,
+    pub clear_color: Color,
+    pub viewport_outline: Option<Color>,
+    pub overlay: Option<Overlay>,
+}
+
+impl RenderPass {
+    const OUTLINE_WIDTH: f32 = 2.0;
+
+    pub fn new(width: u32, height: u32, transform: Transform, drawings: Vec<String>) -> RenderPass {
+        RenderPass {
+            width,
+            height,
+            transform,
+            drawings,
+            every: 1,
+            clear_color: Color::new(0.0, 0.0, 0.0, 1.0),
+            viewport_outline: None,
+            overlay: None,
+        }
+    }
+
+    pub fn minimap(
+        width: u32,
+        height: u32,
+        from: WorldCoord,
+        to: WorldCoord,
+        max_z: f32,
+        drawings: Vec<String>,
+    ) -> RenderPass {
+        let overlay = Overlay::bottom_right(width as f32, height as f32, 8.0);
+        RenderPass::new(
+            width,
+            height,
+            RenderPass::top_down(from, to, max_z),
+            drawings,
+        )
+        .with_viewport_outline(Color::new(1.0, 1.0, 1.0, 1.0))
+        .with_overlay(overlay)
+    }
+
+    pub fn top_down(from: WorldCoord, to: WorldCoord, max_z: f32) -> Transform {
+        let scale_x = 2.0 / (to.x - from.x);
+        let scale_y = 2.0 / (to.y - from.y);
+        Transform::new(
+            GLCoord3D::new(scale_x, scale_y, 1.0 / max_z),
+            GLCoord2D::new(-1.0 - from.x * scale_x, 1.0 + from.y * scale_y),
+            Box::new(Isometric::new(0.0, 0.0)),
+        )
+    }
+
+    pub fn with_every(mut self, every: u32) -> RenderPass {
+        self.every = every.max(1);
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: Color) -> RenderPass {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn with_viewport_outline(mut self, color: Color) -> RenderPass {
+        self.viewport_outline = Some(color);
+        self
+    }
+
+    pub fn with_overlay(mut self, overlay: Overlay) -> RenderPass {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    pub fn outline(&self, view: &Transform, color: Color) -> DrawingDescription {
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let corners: Vec<(f32, f32)> = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(|corner| {
+                let world_coord = view.unproject(GLCoord4D::new(corner.0, corner.1, 0.0, 1.0));
+                let gl_coord = self.transform.project(world_coord);
+                (
+                    (gl_coord.x + 1.0) * width / 2.0,
+                    (gl_coord.y + 1.0) * height / 2.0,
+                )
+            })
+            .collect();
+
+        let mut vertices = vec![];
+        for i in 0..4 {
+            let (ax, ay) = corners[i];
+            let (bx, by) = corners[(i + 1) % 4];
+            let length = ((bx - ax).powi(2) + (by - ay).powi(2)).sqrt().max(1e-6);
+            let nx = -(by - ay) / length * RenderPass::OUTLINE_WIDTH / 2.0;
+            let ny = (bx - ax) / length * RenderPass::OUTLINE_WIDTH / 2.0;
+            let quad = [
+                (ax - nx, ay - ny),
+                (bx - nx, by - ny),
+                (bx + nx, by + ny),
+                (ax - nx, ay - ny),
+                (bx + nx, by + ny),
+                (ax + nx, ay + ny),
+            ];
+            for (x, y) in quad.iter() {
+                vertices.append(&mut vec![
+                    x * 2.0 / width - 1.0,
+                    y * 2.0 / height - 1.0,
+                    -1.0,
+                    color.r,
+                    color.g,
+                    color.b,
+                ]);
+            }
+        }
+
+        DrawingDescription::plain(vertices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice, RenderDevice};
+    use super::super::engine::{DrawOrder, DrawingType};
+    use super::*;
+    use engine::Command;
+    use headless::HeadlessEngine;
+    use image::Rgba;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_new_render_target_attaches_texture_and_restores_binding() {
+        let device = RecordingDevice::install();
+        device.bind_framebuffer(7);
+        device.clear_calls();
+
+        let target = RenderTarget::new(16, 8).unwrap();
+
+        let texture = target.texture().id();
+        let calls = device.calls();
+        assert!(calls.contains(&Call::TextureImage {
+            width: 16,
+            height: 8
+        }));
+        assert!(calls.contains(&Call::AttachToFramebuffer {
+            texture,
+            depth_buffer: texture + 2
+        }));
+        assert_eq!(calls.last(), Some(&Call::BindFramebuffer(7)));
+        assert_eq!((target.width(), target.height()), (16, 8));
+    }
+
+    #[test]
+    fn test_top_down_transform_fits_bounds() {
+        let transform = RenderPass::top_down(
+            WorldCoord::new(2.0, 4.0, 0.0),
+            WorldCoord::new(6.0, 12.0, 0.0),
+            10.0,
+        );
+
+        let top_left = transform.project(WorldCoord::new(2.0, 4.0, 0.0));
+        let bottom_right = transform.project(WorldCoord::new(6.0, 12.0, 5.0));
+
+        assert!((top_left.x + 1.0).abs() < 1e-6);
+        assert!((top_left.y - 1.0).abs() < 1e-6);
+        assert!((bottom_right.x - 1.0).abs() < 1e-6);
+        assert!((bottom_right.y + 1.0).abs() < 1e-6);
+        assert!((bottom_right.z + 0.5).abs() < 1e-6);
+    }
+
+    #[rustfmt::skip]
+    fn square(color: (f32, f32, f32)) -> Vec<f32> {
+        let (r, g, b) = color;
+        vec![
+            -1.0, -1.0, 0.0, r, g, b,
+            1.0, -1.0, 0.0, r, g, b,
+            1.0, 1.0, 0.0, r, g, b,
+            -1.0, -1.0, 0.0, r, g, b,
+            1.0, 1.0, 0.0, r, g, b,
+            -1.0, 1.0, 0.0, r, g, b,
+        ]
+    }
+
+    #[test]
+    fn test_minimap() {
+        let mut engine = HeadlessEngine::new(64, 64, 100.0).unwrap();
+        engine.set_transform(Transform::new(
+            GLCoord3D::new(8.0, 8.0, 0.01),
+            GLCoord2D::new(0.0, 0.0),
+            Box::new(Isometric::new(PI / 4.0, PI / 3.0)),
+        ));
+        engine
+            .handle_command(Command::DrawDescription {
+                name: "square".to_string(),
+                description: DrawingDescription::plain(square((1.0, 0.0, 0.0))),
+            })
+            .unwrap();
+        let pass = RenderPass::minimap(
+            16,
+            16,
+            WorldCoord::new(-2.0, -2.0, 0.0),
+            WorldCoord::new(2.0, 2.0, 0.0),
+            100.0,
+            vec!["square".to_string()],
+        );
+        engine
+            .handle_command(Command::AddRenderPass {
+                name: "minimap".to_string(),
+                pass,
+            })
+            .unwrap();
+
+        engine
+            .handle_command(Command::SetDrawOrder(DrawOrder {
+                world: vec![DrawingType::Plain],
+                ui: vec![],
+            }))
+            .unwrap();
+
+        let image = engine.render().unwrap();
+
+        let overlay = |x: u32, y: u32| *image.get_pixel(64 - 8 - 16 + x, 64 - 8 - 16 + y);
+        assert_eq!(overlay(0, 0), Rgba([0, 0, 0, 255]));
+        assert_eq!(overlay(15, 15), Rgba([0, 0, 0, 255]));
+        assert_eq!(overlay(5, 10), Rgba([255, 0, 0, 255]));
+        assert_eq!(overlay(8, 8), Rgba([255, 255, 255, 255]));
+        assert_eq!(*image.get_pixel(0, 0), Rgba([255, 0, 0, 255]));
+    }
+}
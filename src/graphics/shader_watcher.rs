@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new(directory: PathBuf) -> ShaderWatcher {
+        ShaderWatcher {
+            directory,
+            modified: HashMap::new(),
+        }
+    }
+
+    pub fn paths(&self, name: &str) -> (PathBuf, PathBuf) {
+        (
+            self.directory.join(format!("{}.vert", name)),
+            self.directory.join(format!("{}.frag", name)),
+        )
+    }
+
+    fn update_modified(&mut self, path: &PathBuf) -> Option<bool> {
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        let previous = self.modified.insert(path.clone(), modified);
+        Some(previous != Some(modified))
+    }
+
+    pub fn changed(&mut self, name: &str) -> Option<Result<(String, String), String>> {
+        let (vertex, fragment) = self.paths(name);
+        let vertex_changed = self.update_modified(&vertex)?;
+        let fragment_changed = self.update_modified(&fragment)?;
+        if !vertex_changed && !fragment_changed {
+            return None;
+        }
+        let read = |path: &PathBuf| {
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))
+        };
+        Some(read(&vertex).and_then(|vertex| Ok((vertex, read(&fragment)?))))
+    }
+}
+
+fn modified_times(directory: &PathBuf) -> HashMap<PathBuf, SystemTime> {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return HashMap::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            modified.ok().map(|modified| (entry.path(), modified))
+        })
+        .collect()
+}
+
+// Polls off the render thread so an idle engine only wakes when a shader file changes
+pub struct ShaderWakeThread {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ShaderWakeThread {
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn new(directory: PathBuf, wake: Box<Fn() + Send>) -> ShaderWakeThread {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            let mut modified = modified_times(&directory);
+            while let Err(RecvTimeoutError::Timeout) =
+                stopped.recv_timeout(ShaderWakeThread::POLL_INTERVAL)
+            {
+                let latest = modified_times(&directory);
+                if latest != modified {
+                    modified = latest;
+                    wake();
+                }
+            }
+        });
+        ShaderWakeThread {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for ShaderWakeThread {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+    use std::fs::File;
+    use std::thread;
+    use std::time::Duration;
+
+    fn directory(name: &str) -> PathBuf {
+        let out = env::temp_dir().join(format!(
+            "isometric-shaders-{}-{:?}",
+            name,
+            thread::current().id()
+        ));
+        fs::create_dir_all(&out).unwrap();
+        out
+    }
+
+    fn touch(path: &PathBuf, seconds: u64) {
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_changed() {
+        let directory = directory("changed");
+        let mut watcher = ShaderWatcher::new(directory.clone());
+        let (vertex, fragment) = watcher.paths("plain");
+        fs::write(&vertex, "vertex").unwrap();
+        fs::write(&fragment, "fragment").unwrap();
+
+        assert_eq!(
+            watcher.changed("plain"),
+            Some(Ok(("vertex".to_string(), "fragment".to_string())))
+        );
+        assert_eq!(watcher.changed("plain"), None);
+
+        fs::write(&fragment, "new fragment").unwrap();
+        touch(&fragment, 1);
+        assert_eq!(
+            watcher.changed("plain"),
+            Some(Ok(("vertex".to_string(), "new fragment".to_string())))
+        );
+        assert_eq!(watcher.changed("plain"), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_wake_thread_wakes_only_on_change() {
+        let directory = directory("wake");
+        let vertex = directory.join("plain.vert");
+        fs::write(&vertex, "vertex").unwrap();
+        let (woken, wakes) = mpsc::channel();
+        let wake_thread =
+            ShaderWakeThread::new(directory.clone(), Box::new(move || woken.send(()).unwrap()));
+
+        assert!(wakes.recv_timeout(Duration::from_millis(600)).is_err());
+
+        touch(&vertex, 1);
+        assert_eq!(wakes.recv_timeout(Duration::from_secs(2)), Ok(()));
+
+        drop(wake_thread);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_missing_shaders_are_not_watched() {
+        let directory = directory("missing");
+        let mut watcher = ShaderWatcher::new(directory.clone());
+        fs::write(watcher.paths("text").0, "vertex").unwrap();
+
+        assert_eq!(watcher.changed("text"), None);
+        assert_eq!(watcher.changed("billboard"), None);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -1,26 +1,32 @@
-use image::{DynamicImage, GenericImageView};
-use std::ffi::c_void;
+use super::device::{device, RenderDevice};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
 use {v2, V2};
 
 pub struct Texture {
-    id: gl::types::GLuint,
+    device: Rc<RenderDevice>,
+    id: u32,
     width: u32,
     height: u32,
 }
 
 impl Texture {
-    pub fn new(image: DynamicImage) -> Texture {
-        let mut id: gl::types::GLuint = 0;
-        unsafe {
-            gl::GenTextures(1, &mut id);
-            let mut out = Texture {
-                id,
-                width: 0,
-                height: 0,
-            };
-            out.load(image);
-            out
-        }
+    pub fn new(image: &RgbaImage) -> Texture {
+        let device = device();
+        let mut out = Texture {
+            id: device.create_texture(),
+            device,
+            width: 0,
+            height: 0,
+        };
+        out.load(image);
+        out
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
     }
 
     pub fn width(&self) -> u32 {
@@ -31,40 +37,21 @@ impl Texture {
         self.height
     }
 
-    pub unsafe fn bind(&self) {
-        gl::BindTexture(gl::TEXTURE_2D, self.id);
-    }
-
-    pub unsafe fn unbind(&self) {
-        gl::BindTexture(gl::TEXTURE_2D, 0);
-    }
-
-    fn load(&mut self, image: DynamicImage) {
-        let dimensions = image.dimensions();
-        self.width = dimensions.0;
-        self.height = dimensions.1;
-        let image = image.to_rgba().into_raw();
-        let image_ptr: *const c_void = image.as_ptr() as *const c_void;
-
-        unsafe {
-            self.bind();
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexImage2D(
-                gl::TEXTURE_2D,
-                0,
-                gl::RGBA as i32,
-                self.width as i32,
-                self.height as i32,
-                0,
-                gl::RGBA,
-                gl::UNSIGNED_BYTE,
-                image_ptr,
-            );
-            self.unbind();
-        }
+    pub fn bind(&self) {
+        self.device.bind_texture(self.id);
+    }
+
+    pub fn unbind(&self) {
+        self.device.bind_texture(0);
+    }
+
+    fn load(&mut self, image: &RgbaImage) {
+        self.width = image.width();
+        self.height = image.height();
+
+        self.bind();
+        self.device.texture_image(self.width, self.height, image);
+        self.unbind();
     }
 
     pub fn get_texture_coords(&self, pixel_position: V2<i32>) -> V2<f32> {
@@ -79,8 +66,93 @@ impl Texture {
 
 impl Drop for Texture {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &mut self.id);
+        self.device.delete_texture(self.id);
+    }
+}
+
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<usize, (Weak<RgbaImage>, Rc<Texture>)>,
+}
+
+impl TextureCache {
+    pub fn new() -> TextureCache {
+        TextureCache::default()
+    }
+
+    // Entries are keyed by image address, so dead images are pruned before their address is reused
+    pub fn get(&mut self, image: &Arc<RgbaImage>) -> Rc<Texture> {
+        self.textures
+            .retain(|_, (cached, _)| cached.upgrade().is_some());
+        let key = Arc::as_ptr(image) as usize;
+        if let Some((_, texture)) = self.textures.get(&key) {
+            return texture.clone();
         }
+        let texture = Rc::new(Texture::new(image));
+        self.textures
+            .insert(key, (Arc::downgrade(image), texture.clone()));
+        texture
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice};
+    use super::*;
+
+    #[test]
+    fn test_cache_uploads_each_image_once() {
+        let device = RecordingDevice::install();
+        let mut cache = TextureCache::new();
+        let image = Arc::new(RgbaImage::new(4, 2));
+
+        let first = cache.get(&image);
+        let second = cache.get(&image.clone());
+
+        assert_eq!(first.id(), second.id());
+        let uploads = device
+            .calls()
+            .into_iter()
+            .filter(|call| {
+                *call
+                    == Call::TextureImage {
+                        width: 4,
+                        height: 2,
+                    }
+            })
+            .count();
+        assert_eq!(uploads, 1);
+    }
+
+    #[test]
+    fn test_texture_is_deleted_on_the_device_that_created_it() {
+        let original = RecordingDevice::install();
+        let texture = Texture::new(&RgbaImage::new(1, 1));
+        let replacement = RecordingDevice::install();
+
+        drop(texture);
+
+        assert_eq!(original.calls().last(), Some(&Call::DeleteTexture(1)));
+        assert_eq!(replacement.calls(), vec![]);
+    }
+
+    #[test]
+    fn test_cache_forgets_dropped_images() {
+        let _device = RecordingDevice::install();
+        let mut cache = TextureCache::new();
+        cache.get(&Arc::new(RgbaImage::new(1, 1)));
+
+        cache.get(&Arc::new(RgbaImage::new(2, 2)));
+
+        assert_eq!(cache.len(), 1);
     }
 }
@@ -1,12 +1,45 @@
+use super::device::{as_bytes, device, BufferTarget, RenderDevice, VertexAttribute};
 use super::engine::DrawingType;
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 fn get_bytes<T>(floats: usize) -> usize {
     floats * std::mem::size_of::<T>()
 }
 
+#[derive(Debug, PartialEq)]
+pub enum BufferError {
+    IndexOutOfBounds { index: usize, indices: usize },
+    TooLarge { bytes: usize, max_bytes: usize },
+    SlabOutOfBounds { x: usize, y: usize },
+}
+
+fn reallocate_buffer<T>(
+    device: &RenderDevice,
+    old_id: u32,
+    size: usize,
+    copies: &[SlotCopy],
+) -> u32 {
+    let id = device.create_buffer();
+    device.bind_buffer(BufferTarget::CopyWrite, id);
+    device.buffer_data(BufferTarget::CopyWrite, get_bytes::<T>(size), None);
+    device.bind_buffer(BufferTarget::CopyRead, old_id);
+    for copy in copies {
+        device.copy_buffer_sub_data(
+            get_bytes::<T>(copy.from),
+            get_bytes::<T>(copy.to),
+            get_bytes::<T>(copy.length),
+        );
+    }
+    device.bind_buffer(BufferTarget::CopyRead, 0);
+    device.bind_buffer(BufferTarget::CopyWrite, 0);
+    device.delete_buffer(old_id);
+    id
+}
+
 pub struct VBO {
-    id: gl::types::GLuint,
+    device: Rc<RenderDevice>,
+    id: u32,
     vao: VAO,
     floats: usize,
 }
@@ -15,26 +48,24 @@ impl VBO {
     const MAX_BYTES: usize = 2147483648;
 
     pub fn new(drawing_type: DrawingType) -> VBO {
-        let mut id: gl::types::GLuint = 0;
+        let device = device();
         let vao = VAO::new(drawing_type);
-        unsafe {
-            gl::GenBuffers(1, &mut id);
-            let out = VBO { id, vao, floats: 0 };
-            out.set_vao();
-            out
-        }
+        let out = VBO {
+            id: device.create_buffer(),
+            device,
+            vao,
+            floats: 0,
+        };
+        out.set_vao();
+        out
     }
 
     fn bind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.id);
-        }
+        self.device.bind_buffer(BufferTarget::Array, self.id);
     }
 
     fn unbind(&self) {
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        }
+        self.device.bind_buffer(BufferTarget::Array, 0);
     }
 
     fn set_vao(&self) {
@@ -65,14 +96,11 @@ impl VBO {
         VBO::check_floats_against_max_bytes(floats.len());
         self.floats = floats.len();
         self.bind();
-        unsafe {
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                get_bytes::<f32>(self.floats) as gl::types::GLsizeiptr,
-                floats.as_ptr() as *const gl::types::GLvoid,
-                gl::STATIC_DRAW,
-            );
-        }
+        self.device.buffer_data(
+            BufferTarget::Array,
+            get_bytes::<f32>(self.floats),
+            Some(as_bytes(&floats)),
+        );
         self.unbind();
     }
 
@@ -80,17 +108,18 @@ impl VBO {
         VBO::check_floats_against_max_bytes(floats);
         self.floats = floats;
         self.bind();
-        unsafe {
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                get_bytes::<f32>(self.floats) as gl::types::GLsizeiptr,
-                std::ptr::null(),
-                gl::STATIC_DRAW,
-            );
-        }
+        self.device
+            .buffer_data(BufferTarget::Array, get_bytes::<f32>(self.floats), None);
         self.unbind();
     }
 
+    fn reallocate(&mut self, floats: usize, copies: &[SlotCopy]) {
+        VBO::check_floats_against_max_bytes(floats);
+        self.id = reallocate_buffer::<f32>(self.device.as_ref(), self.id, floats, copies);
+        self.floats = floats;
+        self.set_vao();
+    }
+
     fn load_part(&self, float_offset: usize, floats: Vec<f32>) {
         if float_offset + floats.len() > self.floats {
             panic!(
@@ -101,210 +130,704 @@ impl VBO {
             );
         }
         self.bind();
-        unsafe {
-            gl::BufferSubData(
-                gl::ARRAY_BUFFER,
-                get_bytes::<f32>(float_offset) as gl::types::GLsizeiptr,
-                get_bytes::<f32>(floats.len()) as gl::types::GLsizeiptr,
-                floats.as_ptr() as *const gl::types::GLvoid,
-            );
-        }
+        self.device.buffer_sub_data(
+            BufferTarget::Array,
+            get_bytes::<f32>(float_offset),
+            as_bytes(&floats),
+        );
         self.unbind();
     }
 
     pub fn draw(&self) {
         if self.floats > 0 {
             self.vao.bind();
-            unsafe {
-                gl::DrawArrays(self.vao.get_draw_mode(), 0, self.floats as i32);
-            }
+            self.device.draw_arrays(self.count_verticies(self.floats));
             self.vao.unbind();
         }
     }
+}
 
-    fn draw_parts(&self, float_offset_increment: usize, floats_vec: &Vec<usize>) {
-        self.vao.bind();
-        let mut float_offset = 0;
-        for floats in floats_vec {
-            let floats = *floats;
-            if floats > 0 {
-                if float_offset + floats > self.floats {
-                    panic!(
-                        "Trying to draw {} floats starting at {} from a buffer with only {} floats",
-                        floats, float_offset, self.floats
-                    );
-                }
-                unsafe {
-                    gl::DrawArrays(
-                        self.vao.get_draw_mode(),
-                        self.count_verticies(float_offset) as i32,
-                        self.count_verticies(floats) as i32,
-                    );
-                }
-            }
-            float_offset += float_offset_increment;
+impl Drop for VBO {
+    fn drop(&mut self) {
+        self.device.delete_buffer(self.id);
+    }
+}
+
+// Named like VBO and VAO rather than following upper_case_acronyms
+#[allow(clippy::upper_case_acronyms)]
+pub struct EBO {
+    device: Rc<RenderDevice>,
+    id: u32,
+    elements: usize,
+}
+
+impl EBO {
+    pub fn new() -> EBO {
+        let device = device();
+        EBO {
+            id: device.create_buffer(),
+            device,
+            elements: 0,
         }
-        self.vao.unbind();
+    }
+
+    fn bind(&self) {
+        self.device.bind_buffer(BufferTarget::ElementArray, self.id);
+    }
+
+    fn check_elements_against_max_bytes(elements: usize) {
+        if get_bytes::<u32>(elements) > VBO::MAX_BYTES {
+            panic!(
+                "Trying to create an EBO with {} bytes. Max allowed is {}.",
+                get_bytes::<u32>(elements),
+                VBO::MAX_BYTES
+            );
+        }
+    }
+
+    fn load(&mut self, elements: Vec<u32>) {
+        EBO::check_elements_against_max_bytes(elements.len());
+        self.elements = elements.len();
+        self.bind();
+        self.device.buffer_data(
+            BufferTarget::ElementArray,
+            get_bytes::<u32>(self.elements),
+            Some(as_bytes(&elements)),
+        );
+    }
+
+    fn alloc(&mut self, elements: usize) {
+        EBO::check_elements_against_max_bytes(elements);
+        self.elements = elements;
+        self.bind();
+        self.device.buffer_data(
+            BufferTarget::ElementArray,
+            get_bytes::<u32>(self.elements),
+            None,
+        );
+    }
+
+    fn reallocate(&mut self, elements: usize, copies: &[SlotCopy]) {
+        EBO::check_elements_against_max_bytes(elements);
+        self.id = reallocate_buffer::<u32>(self.device.as_ref(), self.id, elements, copies);
+        self.elements = elements;
+    }
+
+    fn load_part(&self, element_offset: usize, elements: Vec<u32>) {
+        if element_offset + elements.len() > self.elements {
+            panic!(
+                "Trying to load {} elements at {} in buffer with only {} elements",
+                elements.len(),
+                element_offset,
+                self.elements
+            );
+        }
+        self.bind();
+        self.device.buffer_sub_data(
+            BufferTarget::ElementArray,
+            get_bytes::<u32>(element_offset),
+            as_bytes(&elements),
+        );
     }
 }
 
-impl Drop for VBO {
+impl Drop for EBO {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteBuffers(1, &mut self.id);
+        self.device.delete_buffer(self.id);
+    }
+}
+
+pub struct IndexedVBO {
+    vbo: VBO,
+    ebo: EBO,
+}
+
+impl IndexedVBO {
+    pub fn new(drawing_type: DrawingType) -> IndexedVBO {
+        IndexedVBO {
+            vbo: VBO::new(drawing_type),
+            ebo: EBO::new(),
         }
     }
+
+    pub fn drawing_type(&self) -> &DrawingType {
+        self.vbo.drawing_type()
+    }
+
+    pub fn load(&mut self, floats: Vec<f32>, elements: Vec<u32>) {
+        self.vbo.load(floats);
+        self.vbo.vao.bind();
+        self.ebo.load(elements);
+        self.vbo.vao.unbind();
+    }
+
+    fn alloc(&mut self, floats: usize, elements: usize) {
+        self.vbo.alloc(floats);
+        self.vbo.vao.bind();
+        self.ebo.alloc(elements);
+        self.vbo.vao.unbind();
+    }
+
+    fn load_part(
+        &self,
+        float_offset: usize,
+        floats: Vec<f32>,
+        element_offset: usize,
+        elements: Vec<u32>,
+    ) {
+        self.vbo.load_part(float_offset, floats);
+        self.vbo.vao.bind();
+        self.ebo.load_part(element_offset, elements);
+        self.vbo.vao.unbind();
+    }
+
+    pub fn draw(&self) {
+        if self.ebo.elements > 0 {
+            self.vbo.vao.bind();
+            self.ebo.device.draw_elements(self.ebo.elements);
+            self.vbo.vao.unbind();
+        }
+    }
+
+    fn reallocate(
+        &mut self,
+        floats: Option<(usize, Vec<SlotCopy>)>,
+        elements: Option<(usize, Vec<SlotCopy>)>,
+    ) {
+        if let Some((floats, copies)) = floats {
+            self.vbo.reallocate(floats, &copies);
+        }
+        if let Some((elements, copies)) = elements {
+            self.ebo.reallocate(elements, &copies);
+            self.vbo.vao.bind();
+            self.ebo.bind();
+            self.vbo.vao.unbind();
+        }
+    }
+
+    fn draw_slots(&self, float_slots: &[Slot], element_slots: &[Slot], visible: &[bool]) {
+        let device = &self.vbo.device;
+        self.vbo.vao.bind();
+        for (index, (float_slot, element_slot)) in
+            float_slots.iter().zip(element_slots.iter()).enumerate()
+        {
+            if element_slot.used > 0 && visible[index] {
+                device.draw_elements_base_vertex(
+                    element_slot.used,
+                    get_bytes::<u32>(element_slot.offset),
+                    self.vbo.count_verticies(float_slot.offset),
+                );
+            }
+        }
+        self.vbo.vao.unbind();
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Slot {
+    offset: usize,
+    capacity: usize,
+    used: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct SlotCopy {
+    from: usize,
+    to: usize,
+    length: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Allocation {
+    InPlace,
+    Appended,
+    Reallocated {
+        capacity: usize,
+        copies: Vec<SlotCopy>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct SlotAllocator {
+    slots: Vec<Slot>,
+    capacity: usize,
+    end: usize,
+}
+
+impl SlotAllocator {
+    fn new(slots: usize, capacity_per_slot: usize) -> SlotAllocator {
+        SlotAllocator {
+            slots: (0..slots)
+                .map(|slot| Slot {
+                    offset: slot * capacity_per_slot,
+                    capacity: capacity_per_slot,
+                    used: 0,
+                })
+                .collect(),
+            capacity: slots * capacity_per_slot,
+            end: slots * capacity_per_slot,
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        index: usize,
+        size: usize,
+        max_capacity: usize,
+    ) -> Result<Allocation, usize> {
+        if size <= self.slots[index].capacity {
+            self.slots[index].used = size;
+            Ok(Allocation::InPlace)
+        } else if self.end + size <= self.capacity {
+            self.slots[index] = Slot {
+                offset: self.end,
+                capacity: size,
+                used: size,
+            };
+            self.end += size;
+            Ok(Allocation::Appended)
+        } else {
+            let (slots, copies, end) = self.compacted(Some((index, size)));
+            if end > max_capacity {
+                return Err(end);
+            }
+            self.slots = slots;
+            self.end = end;
+            self.capacity = (end * 2).min(max_capacity);
+            Ok(Allocation::Reallocated {
+                capacity: self.capacity,
+                copies,
+            })
+        }
+    }
+
+    fn compact(&mut self) -> Allocation {
+        let (slots, copies, end) = self.compacted(None);
+        self.slots = slots;
+        self.end = end;
+        self.capacity = end;
+        Allocation::Reallocated {
+            capacity: self.capacity,
+            copies,
+        }
+    }
+
+    fn compacted(&self, resized: Option<(usize, usize)>) -> (Vec<Slot>, Vec<SlotCopy>, usize) {
+        let mut slots = vec![];
+        let mut copies = vec![];
+        let mut end = 0;
+        for (index, slot) in self.slots.iter().enumerate() {
+            let slot = match resized {
+                Some((resized, size)) if resized == index => Slot {
+                    offset: end,
+                    capacity: size,
+                    used: size,
+                },
+                _ => {
+                    if slot.used > 0 {
+                        copies.push(SlotCopy {
+                            from: slot.offset,
+                            to: end,
+                            length: slot.used,
+                        });
+                    }
+                    Slot {
+                        offset: end,
+                        capacity: slot.capacity,
+                        used: slot.used,
+                    }
+                }
+            };
+            end += slot.capacity;
+            slots.push(slot);
+        }
+        (slots, copies, end)
+    }
+
+    fn free(&self) -> usize {
+        self.capacity - self.slots.iter().map(|slot| slot.used).sum::<usize>()
+    }
+}
+
+fn reallocation(allocation: &Allocation) -> Option<(usize, Vec<SlotCopy>)> {
+    match allocation {
+        Allocation::Reallocated { capacity, copies } => Some((*capacity, copies.clone())),
+        _ => None,
+    }
+}
+
+struct MultiVBOState {
+    vbo: IndexedVBO,
+    floats: SlotAllocator,
+    elements: SlotAllocator,
+}
+
+impl MultiVBOState {
+    fn load(
+        &mut self,
+        index: usize,
+        floats: Vec<f32>,
+        elements: Vec<u32>,
+    ) -> Result<(), BufferError> {
+        let mut float_slots = self.floats.clone();
+        let float_allocation = float_slots
+            .allocate(index, floats.len(), VBO::MAX_BYTES / get_bytes::<f32>(1))
+            .map_err(|floats| BufferError::TooLarge {
+                bytes: get_bytes::<f32>(floats),
+                max_bytes: VBO::MAX_BYTES,
+            })?;
+        let mut element_slots = self.elements.clone();
+        let element_allocation = element_slots
+            .allocate(index, elements.len(), VBO::MAX_BYTES / get_bytes::<u32>(1))
+            .map_err(|elements| BufferError::TooLarge {
+                bytes: get_bytes::<u32>(elements),
+                max_bytes: VBO::MAX_BYTES,
+            })?;
+        self.floats = float_slots;
+        self.elements = element_slots;
+        self.vbo.reallocate(
+            reallocation(&float_allocation),
+            reallocation(&element_allocation),
+        );
+        self.vbo.load_part(
+            self.floats.slots[index].offset,
+            floats,
+            self.elements.slots[index].offset,
+            elements,
+        );
+        Ok(())
+    }
+
+    fn compact(&mut self) {
+        let float_allocation = self.floats.compact();
+        let element_allocation = self.elements.compact();
+        self.vbo.reallocate(
+            reallocation(&float_allocation),
+            reallocation(&element_allocation),
+        );
+    }
 }
 
 #[derive(Clone)]
 pub struct MultiVBO {
-    vbo: Arc<VBO>,
+    state: Rc<RefCell<MultiVBOState>>,
+    drawing_type: DrawingType,
     indices: usize,
-    max_floats_per_index: usize,
-    floats_at_index: Vec<usize>,
 }
 
 impl MultiVBO {
-    pub fn new(drawing_type: DrawingType, indices: usize, max_floats_per_index: usize) -> MultiVBO {
-        let mut vbo = VBO::new(drawing_type);
-        vbo.alloc(indices * max_floats_per_index);
+    pub fn new(
+        drawing_type: DrawingType,
+        indices: usize,
+        max_floats_per_index: usize,
+        max_elements_per_index: usize,
+    ) -> MultiVBO {
+        let mut vbo = IndexedVBO::new(drawing_type);
+        vbo.alloc(
+            indices * max_floats_per_index,
+            indices * max_elements_per_index,
+        );
         MultiVBO {
-            vbo: Arc::new(vbo),
+            state: Rc::new(RefCell::new(MultiVBOState {
+                vbo,
+                floats: SlotAllocator::new(indices, max_floats_per_index),
+                elements: SlotAllocator::new(indices, max_elements_per_index),
+            })),
+            drawing_type,
             indices,
-            max_floats_per_index,
-            floats_at_index: vec![0; indices],
         }
     }
 
-    pub fn load(&mut self, index: usize, floats: Vec<f32>) {
-        self.floats_at_index[index] = floats.len();
-        self.vbo
-            .load_part(index * self.max_floats_per_index, floats);
+    pub fn load(
+        &mut self,
+        index: usize,
+        floats: Vec<f32>,
+        elements: Vec<u32>,
+    ) -> Result<(), BufferError> {
+        if index >= self.indices {
+            return Err(BufferError::IndexOutOfBounds {
+                index,
+                indices: self.indices,
+            });
+        }
+        self.state.borrow_mut().load(index, floats, elements)
     }
 
-    pub fn draw(&self) {
-        self.vbo
-            .draw_parts(self.max_floats_per_index, &self.floats_at_index);
+    pub fn compact(&mut self) {
+        self.state.borrow_mut().compact();
+    }
+
+    pub fn free_bytes(&self) -> usize {
+        let state = self.state.borrow();
+        get_bytes::<f32>(state.floats.free()) + get_bytes::<u32>(state.elements.free())
+    }
+
+    pub fn draw(&self, visible: &[bool]) {
+        let state = self.state.borrow();
+        state
+            .vbo
+            .draw_slots(&state.floats.slots, &state.elements.slots, visible);
     }
 
     pub fn drawing_type(&self) -> &DrawingType {
-        &self.vbo.drawing_type()
+        &self.drawing_type
     }
 }
 
 pub struct VAO {
-    id: gl::types::GLuint,
+    device: Rc<RenderDevice>,
+    id: u32,
     drawing_type: DrawingType,
 }
 
 impl VAO {
     pub fn new(drawing_type: DrawingType) -> VAO {
-        let mut id: gl::types::GLuint = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut id);
-        }
+        let device = device();
         VAO {
-            id,
-            drawing_type: drawing_type,
+            id: device.create_vertex_array(),
+            device,
+            drawing_type,
         }
     }
 
     fn set(&self) {
         self.bind();
-        match self.drawing_type {
-            DrawingType::Plain => setup_vao_for_plain_drawing(),
-            DrawingType::Text => setup_vao_for_sprite_drawing(),
-            DrawingType::Billboard => setup_vao_for_sprite_drawing(),
+        for attribute in self.attributes() {
+            self.device.vertex_attribute(attribute);
         }
         self.unbind();
     }
 
-    fn get_draw_mode(&self) -> gl::types::GLenum {
-        match self.drawing_type {
-            _ => gl::TRIANGLES,
-        }
+    fn attributes(&self) -> Vec<VertexAttribute> {
+        let stride = self.floats_per_vertex();
+        let mut offset = 0;
+        self.drawing_type
+            .attributes()
+            .iter()
+            .enumerate()
+            .map(|(index, floats)| {
+                let attribute = VertexAttribute {
+                    index: index as u32,
+                    floats: *floats,
+                    stride,
+                    offset,
+                };
+                offset += floats;
+                attribute
+            })
+            .collect()
     }
 
     pub fn floats_per_vertex(&self) -> usize {
-        match self.drawing_type {
-            DrawingType::Plain => 6,
-            _ => 7,
-        }
+        self.drawing_type.floats_per_vertex()
     }
 
     pub fn bind(&self) {
-        unsafe {
-            gl::BindVertexArray(self.id);
-        }
+        self.device.bind_vertex_array(self.id);
     }
 
     pub fn unbind(&self) {
-        unsafe {
-            gl::BindVertexArray(0);
-        }
+        self.device.bind_vertex_array(0);
     }
 }
 
 impl Drop for VAO {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteVertexArrays(1, &mut self.id);
-        }
+        self.device.delete_vertex_array(self.id);
     }
 }
 
-fn setup_vao_for_plain_drawing() {
-    //TODO why are these not part of VAO?
-    unsafe {
-        gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
-            std::ptr::null(),
+#[cfg(test)]
+mod tests {
+
+    use super::super::device::{Call, RecordingDevice};
+    use super::*;
+
+    #[test]
+    fn test_allocate_in_place() {
+        let mut allocator = SlotAllocator::new(3, 10);
+
+        assert_eq!(allocator.allocate(1, 10, 100), Ok(Allocation::InPlace));
+        assert_eq!(
+            allocator.slots[1],
+            Slot {
+                offset: 10,
+                capacity: 10,
+                used: 10
+            }
         );
-        gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(
-            1,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (6 * std::mem::size_of::<f32>()) as gl::types::GLint,
-            (3 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid,
+    }
+
+    #[test]
+    fn test_allocate_appends_to_free_space() {
+        let mut allocator = SlotAllocator::new(3, 10);
+        allocator.allocate(0, 12, 100).unwrap();
+        allocator.compact();
+        allocator.capacity = 50;
+
+        assert_eq!(allocator.allocate(1, 15, 100), Ok(Allocation::Appended));
+        assert_eq!(
+            allocator.slots[1],
+            Slot {
+                offset: 32,
+                capacity: 15,
+                used: 15
+            }
         );
+        assert_eq!(allocator.end, 47);
     }
-}
 
-fn setup_vao_for_sprite_drawing() {
-    unsafe {
-        gl::EnableVertexAttribArray(0);
-        gl::VertexAttribPointer(
-            0,
-            3,
-            gl::FLOAT,
-            gl::FALSE,
-            (7 * std::mem::size_of::<f32>()) as gl::types::GLint,
-            std::ptr::null(),
+    #[test]
+    fn test_allocate_reallocates_and_compacts() {
+        let mut allocator = SlotAllocator::new(3, 10);
+        allocator.allocate(0, 4, 100).unwrap();
+        allocator.allocate(2, 6, 100).unwrap();
+
+        assert_eq!(
+            allocator.allocate(1, 20, 100),
+            Ok(Allocation::Reallocated {
+                capacity: 80,
+                copies: vec![
+                    SlotCopy {
+                        from: 0,
+                        to: 0,
+                        length: 4
+                    },
+                    SlotCopy {
+                        from: 20,
+                        to: 30,
+                        length: 6
+                    },
+                ]
+            })
         );
-        gl::EnableVertexAttribArray(1);
-        gl::VertexAttribPointer(
-            1,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            (7 * std::mem::size_of::<f32>()) as gl::types::GLint,
-            (3 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid,
+        assert_eq!(
+            allocator.slots,
+            vec![
+                Slot {
+                    offset: 0,
+                    capacity: 10,
+                    used: 4
+                },
+                Slot {
+                    offset: 10,
+                    capacity: 20,
+                    used: 20
+                },
+                Slot {
+                    offset: 30,
+                    capacity: 10,
+                    used: 6
+                },
+            ]
         );
-        gl::EnableVertexAttribArray(2);
-        gl::VertexAttribPointer(
-            2,
-            2,
-            gl::FLOAT,
-            gl::FALSE,
-            (7 * std::mem::size_of::<f32>()) as gl::types::GLint,
-            (5 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid,
+    }
+
+    #[test]
+    fn test_allocate_growth_limited_by_max_capacity() {
+        let mut allocator = SlotAllocator::new(2, 10);
+
+        assert_eq!(
+            allocator.allocate(0, 20, 35),
+            Ok(Allocation::Reallocated {
+                capacity: 35,
+                copies: vec![]
+            })
         );
     }
+
+    #[test]
+    fn test_allocate_beyond_max_capacity_fails_without_change() {
+        let mut allocator = SlotAllocator::new(2, 10);
+        let before = allocator.clone();
+
+        assert_eq!(allocator.allocate(0, 40, 45), Err(50));
+        assert_eq!(allocator, before);
+    }
+
+    #[test]
+    fn test_compact_removes_free_space() {
+        let mut allocator = SlotAllocator::new(2, 10);
+        allocator.allocate(0, 5, 1000).unwrap();
+        allocator.allocate(1, 30, 1000).unwrap();
+        allocator.allocate(0, 50, 1000).unwrap();
+        assert_eq!(allocator.free(), 80);
+
+        allocator.compact();
+
+        assert_eq!(allocator.capacity, 80);
+        assert_eq!(allocator.free(), 0);
+    }
+
+    #[test]
+    fn test_multi_vbo_draws_visible_slots_from_their_offsets() {
+        let device = RecordingDevice::install();
+        let mut vbo = MultiVBO::new(DrawingType::Plain, 3, 18, 3);
+        vbo.load(0, vec![0.0; 18], vec![0, 1, 2]).unwrap();
+        vbo.load(2, vec![0.0; 12], vec![0, 1]).unwrap();
+        device.clear_calls();
+
+        vbo.draw(&[true, true, true]);
+        vbo.draw(&[false, true, true]);
+
+        assert_eq!(
+            device.draw_calls(),
+            vec![
+                Call::DrawElementsBaseVertex {
+                    elements: 3,
+                    offset: 0,
+                    base_vertex: 0,
+                },
+                Call::DrawElementsBaseVertex {
+                    elements: 2,
+                    offset: 24,
+                    base_vertex: 6,
+                },
+                Call::DrawElementsBaseVertex {
+                    elements: 2,
+                    offset: 24,
+                    base_vertex: 6,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vbo_draws_vertex_count() {
+        let device = RecordingDevice::install();
+        let mut vbo = VBO::new(DrawingType::Text);
+        vbo.load(vec![0.0; 42]);
+        device.clear_calls();
+
+        vbo.draw();
+
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(6)]);
+    }
+
+    #[test]
+    fn test_custom_vertex_layout() {
+        let device = RecordingDevice::install();
+        let drawing_type = DrawingType::Custom {
+            name: "custom",
+            attributes: &[3, 1, 2],
+        };
+
+        let mut vbo = VBO::new(drawing_type);
+        vbo.load(vec![0.0; 12]);
+        vbo.draw();
+
+        let attribute = |index, floats, offset| {
+            Call::VertexAttribute(VertexAttribute {
+                index,
+                floats,
+                stride: 6,
+                offset,
+            })
+        };
+        let calls = device.calls();
+        assert!(calls.contains(&attribute(0, 3, 0)));
+        assert!(calls.contains(&attribute(1, 1, 3)));
+        assert!(calls.contains(&attribute(2, 2, 4)));
+        assert_eq!(device.draw_calls(), vec![Call::DrawArrays(2)]);
+    }
 }
@@ -0,0 +1,506 @@
+use engine::Command;
+use graphics::drawing::Drawing;
+use graphics::egl::{HeadlessContext, HeadlessError};
+use graphics::engine::GraphicsEngine;
+use graphics::framebuffer::Framebuffer;
+use image::RgbaImage;
+use query::EngineView;
+use std::path::PathBuf;
+use transform::Transform;
+
+pub struct HeadlessEngine {
+    graphics: GraphicsEngine,
+    framebuffer: Framebuffer,
+    context: HeadlessContext,
+}
+
+impl HeadlessEngine {
+    const GL_VERSION: (u8, u8) = (3, 3);
+
+    pub fn new(width: u32, height: u32, max_z: f32) -> Result<HeadlessEngine, HeadlessError> {
+        let context = HeadlessContext::new(HeadlessEngine::GL_VERSION)?;
+        unsafe {
+            context.make_current()?;
+            gl::load_with(|symbol| context.get_proc_address(symbol));
+        }
+
+        let framebuffer = Framebuffer::new(width, height)?;
+        let graphics = GraphicsEngine::new(
+            1.0 / max_z,
+            glutin::dpi::PhysicalSize::new(width as f64, height as f64),
+        )?;
+
+        Ok(HeadlessEngine {
+            graphics,
+            framebuffer,
+            context,
+        })
+    }
+
+    pub fn handle_command(&mut self, command: Command) -> Result<(), HeadlessError> {
+        self.make_current()?;
+        match command {
+            Command::Resize(physical_size) => self.resize(physical_size)?,
+            Command::SetWindowSize(logical_size) => self.resize(logical_size.to_physical(1.0))?,
+            Command::Translate(translation) => self.graphics.get_transform().translate(translation),
+            Command::Scale { center, scale } => self.graphics.get_transform().scale(center, scale),
+            Command::Rotate { center, yaw } => self.graphics.rotate(center, yaw),
+            Command::Draw { name, build } => self.graphics.add_drawing(name, build()),
+            Command::DrawDescription { name, description } => {
+                self.graphics.add_description(name, description)
+            }
+            Command::DrawTerrain { name, description } => self
+                .graphics
+                .add_terrain(name, description)
+                .map_err(HeadlessError::Buffer)?,
+            Command::Erase(name) => self.graphics.remove_drawing(&name),
+            Command::AddProgram {
+                drawing_type,
+                vertex_shader,
+                fragment_shader,
+                setup,
+            } => {
+                self.graphics
+                    .add_program(drawing_type, &vertex_shader, &fragment_shader, setup)?;
+            }
+            Command::SetDrawOrder(draw_order) => self.graphics.set_draw_order(draw_order),
+            Command::WatchShaders(directory) => self.graphics.watch_shaders(directory),
+            Command::AddRenderPass { name, pass } => self
+                .graphics
+                .add_render_pass(name, pass)
+                .map_err(HeadlessError::RenderTarget)?,
+            Command::RemoveRenderPass(name) => self.graphics.remove_render_pass(&name),
+            Command::LookAt(world_coord) => self.graphics.get_transform().look_at(world_coord),
+            Command::Screenshot { path, include_ui } => {
+                let image = self.capture(|graphics| graphics.capture(include_ui))?;
+                HeadlessEngine::save(image, path)?;
+            }
+            Command::TiledScreenshot {
+                path,
+                tiles,
+                include_ui,
+            } => {
+                let image = self.capture(|graphics| graphics.capture_tiled(tiles, include_ui))?;
+                HeadlessEngine::save(image.map_err(HeadlessError::Capture)?, path)?;
+            }
+            Command::QueryChannel { kind, reply } => {
+                let _ = reply.send(self.view().query(&kind));
+            }
+            Command::RequestRedraw => (),
+            command => return Err(HeadlessError::Unsupported(unsupported(&command))),
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, physical_size: glutin::dpi::PhysicalSize) -> Result<(), HeadlessError> {
+        self.framebuffer =
+            Framebuffer::new(physical_size.width as u32, physical_size.height as u32)?;
+        self.graphics.set_viewport_size(physical_size);
+        Ok(())
+    }
+
+    pub fn add_drawing(&mut self, name: String, drawing: Box<Drawing>) {
+        self.graphics.add_drawing(name, drawing);
+    }
+
+    pub fn view(&self) -> EngineView<'_> {
+        EngineView::new(&self.graphics)
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.graphics.set_transform(transform);
+    }
+
+    pub fn make_current(&self) -> Result<(), HeadlessError> {
+        unsafe { self.context.make_current() }
+    }
+
+    fn capture<F, T>(&mut self, capture: F) -> Result<T, HeadlessError>
+    where
+        F: FnOnce(&mut GraphicsEngine) -> T,
+    {
+        self.make_current()?;
+        self.framebuffer.bind();
+        let out = capture(&mut self.graphics);
+        self.framebuffer.unbind();
+        Ok(out)
+    }
+
+    fn save(image: RgbaImage, path: PathBuf) -> Result<(), HeadlessError> {
+        image
+            .save(path)
+            .map_err(|err| HeadlessError::Save(err.to_string()))
+    }
+
+    pub fn reload_shaders(&mut self) -> Result<(), HeadlessError> {
+        self.make_current()?;
+        for (_, result) in self.graphics.reload_shaders() {
+            result?;
+        }
+        Ok(())
+    }
+
+    pub fn render(&mut self) -> Result<RgbaImage, HeadlessError> {
+        self.capture(|graphics| graphics.capture(true))
+    }
+
+    pub fn render_tiled(&mut self, tiles: u32) -> Result<RgbaImage, HeadlessError> {
+        self.capture(|graphics| graphics.capture_tiled(tiles, true))?
+            .map_err(HeadlessError::Capture)
+    }
+}
+
+impl Drop for HeadlessEngine {
+    fn drop(&mut self) {
+        let _ = self.make_current();
+    }
+}
+
+fn unsupported(command: &Command) -> &'static str {
+    match command {
+        Command::Shutdown => "Shutdown",
+        Command::Event(..) => "Event",
+        Command::ComputeWorldPosition(..) => "ComputeWorldPosition",
+        Command::StartRecording(..) => "StartRecording",
+        Command::StopRecording => "StopRecording",
+        Command::SetFullscreen(..) => "SetFullscreen",
+        Command::SetTitle(..) => "SetTitle",
+        Command::SetVsync(..) => "SetVsync",
+        Command::GrabCursor(..) => "GrabCursor",
+        Command::HideCursor(..) => "HideCursor",
+        Command::SetRenderMode(..) => "SetRenderMode",
+        Command::Schedule { .. } => "Schedule",
+        Command::StartTimer { .. } => "StartTimer",
+        Command::CancelTimer(..) => "CancelTimer",
+        Command::AddHandler { .. } => "AddHandler",
+        Command::RemoveHandler(..) => "RemoveHandler",
+        Command::ReplaceHandler { .. } => "ReplaceHandler",
+        Command::Query { .. } => "Query",
+        _ => "Command",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use coords::*;
+    use error::Error;
+    use graphics::drawing::{DescribedDrawing, DrawingDescription};
+    use graphics::engine::DrawingType;
+    use image::Rgba;
+    use query::{QueryKind, QueryResult};
+    use snapshot::compare_images;
+    use std::env;
+    use std::f32::consts::PI;
+    use std::fs;
+    use std::sync::mpsc;
+    use transform::Isometric;
+
+    #[rustfmt::skip]
+    fn square(color: (f32, f32, f32)) -> Vec<f32> {
+        let (r, g, b) = color;
+        vec![
+            -1.0, -1.0, 0.0, r, g, b,
+            1.0, -1.0, 0.0, r, g, b,
+            1.0, 1.0, 0.0, r, g, b,
+            -1.0, -1.0, 0.0, r, g, b,
+            1.0, 1.0, 0.0, r, g, b,
+            -1.0, 1.0, 0.0, r, g, b,
+        ]
+    }
+
+    #[test]
+    fn test_render_empty_frame_is_clear_color() {
+        let mut engine = HeadlessEngine::new(32, 16, 100.0).unwrap();
+
+        let image = engine.render().unwrap();
+
+        assert_eq!(image.dimensions(), (32, 16));
+        assert!(image.pixels().all(|pixel| *pixel == Rgba([0, 0, 255, 255])));
+    }
+
+    #[test]
+    fn test_render_drawing() {
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::DrawDescription {
+                name: "square".to_string(),
+                description: DrawingDescription::plain(square((1.0, 0.0, 0.0))),
+            })
+            .unwrap();
+        engine
+            .handle_command(Command::LookAt(WorldCoord::new(0.0, 0.0, 0.0)))
+            .unwrap();
+
+        let image = engine.render().unwrap();
+
+        assert_eq!(*image.get_pixel(16, 16), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_draw_builds_drawing_on_render_thread() {
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::Draw {
+                name: "square".to_string(),
+                build: Box::new(|| {
+                    Box::new(DescribedDrawing::new(
+                        DrawingDescription::plain(square((1.0, 0.0, 0.0))),
+                        None,
+                    ))
+                }),
+            })
+            .unwrap();
+        engine
+            .handle_command(Command::LookAt(WorldCoord::new(0.0, 0.0, 0.0)))
+            .unwrap();
+
+        assert_eq!(
+            *engine.render().unwrap().get_pixel(16, 16),
+            Rgba([255, 0, 0, 255])
+        );
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::Resize(glutin::dpi::PhysicalSize::new(8.0, 4.0)))
+            .unwrap();
+
+        assert_eq!(engine.render().unwrap().dimensions(), (8, 4));
+    }
+
+    #[test]
+    fn test_set_window_size() {
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::SetWindowSize(glutin::dpi::LogicalSize::new(
+                12.0, 6.0,
+            )))
+            .unwrap();
+
+        assert_eq!(engine.render().unwrap().dimensions(), (12, 6));
+    }
+
+    #[test]
+    fn test_tiled_render_matches_larger_render() {
+        let transform = || {
+            Transform::new(
+                GLCoord3D::new(0.5, 0.5, 0.01),
+                GLCoord2D::new(0.0, 0.0),
+                Box::new(Isometric::new(PI / 4.0, PI / 3.0)),
+            )
+        };
+        let draw = || Command::DrawDescription {
+            name: "square".to_string(),
+            description: DrawingDescription::plain(square((1.0, 0.0, 0.0))),
+        };
+        let mut tiled = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        tiled.set_transform(transform());
+        tiled.handle_command(draw()).unwrap();
+        let mut large = HeadlessEngine::new(64, 64, 100.0).unwrap();
+        large.set_transform(transform());
+        large.handle_command(draw()).unwrap();
+
+        let actual = tiled.render_tiled(2).unwrap();
+        let expected = large.render().unwrap();
+
+        assert_eq!(actual.dimensions(), (64, 64));
+        assert!(actual
+            .pixels()
+            .any(|pixel| *pixel == Rgba([255, 0, 0, 255])));
+        assert!(compare_images(&actual, &expected, 2).matches());
+    }
+
+    #[test]
+    fn test_render_tiled_with_no_tiles_is_error() {
+        let mut engine = HeadlessEngine::new(16, 16, 100.0).unwrap();
+
+        match engine.render_tiled(0) {
+            Err(HeadlessError::Capture(_)) => (),
+            _ => panic!("Expected capture error"),
+        }
+        assert_eq!(engine.render().unwrap().dimensions(), (16, 16));
+    }
+
+    #[test]
+    fn test_screenshot() {
+        let path = env::temp_dir().join(format!(
+            "isometric-screenshot-{:?}.png",
+            ::std::thread::current().id()
+        ));
+        let mut engine = HeadlessEngine::new(16, 8, 100.0).unwrap();
+
+        engine
+            .handle_command(Command::Screenshot {
+                path: path.clone(),
+                include_ui: false,
+            })
+            .unwrap();
+
+        let saved = image::open(&path).unwrap().to_rgba();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.dimensions(), (16, 8));
+        assert_eq!(*saved.get_pixel(0, 0), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_custom_program() {
+        const FLAT: DrawingType = DrawingType::Custom {
+            name: "flat",
+            attributes: &[3, 3],
+        };
+        let vertex_shader = "#version 330 core
+            layout (location = 0) in vec3 Position;
+            uniform mat4 projection;
+            void main() { gl_Position = projection * vec4(Position, 1.0); }";
+        let fragment_shader = "#version 330 core
+            uniform float green;
+            out vec4 Color;
+            void main() { Color = vec4(0.0, green, 0.0, 1.0); }";
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::AddProgram {
+                drawing_type: FLAT,
+                vertex_shader: vertex_shader.to_string(),
+                fragment_shader: fragment_shader.to_string(),
+                setup: Box::new(|program, uniforms| {
+                    program.load_matrix4("projection", uniforms.projection);
+                    program.load_float("green", 1.0);
+                }),
+            })
+            .unwrap();
+        engine
+            .handle_command(Command::DrawDescription {
+                name: "square".to_string(),
+                description: DrawingDescription {
+                    drawing_type: FLAT,
+                    ..DrawingDescription::plain(square((1.0, 0.0, 0.0)))
+                },
+            })
+            .unwrap();
+
+        let image = engine.render().unwrap();
+
+        assert_eq!(*image.get_pixel(16, 16), Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_program_error() {
+        let mut engine = HeadlessEngine::new(8, 8, 100.0).unwrap();
+
+        let result = engine.handle_command(Command::AddProgram {
+            drawing_type: DrawingType::Plain,
+            vertex_shader: "not a shader".to_string(),
+            fragment_shader: "not a shader".to_string(),
+            setup: Box::new(|_, _| ()),
+        });
+
+        match result {
+            Err(HeadlessError::Engine(Error::Shader(_))) => (),
+            _ => panic!("Expected program error"),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_command_is_error() {
+        let mut engine = HeadlessEngine::new(8, 8, 100.0).unwrap();
+
+        assert_eq!(
+            engine.handle_command(Command::SetTitle("title".to_string())),
+            Err(HeadlessError::Unsupported("SetTitle"))
+        );
+    }
+
+    #[test]
+    fn test_query_channel() {
+        let mut engine = HeadlessEngine::new(8, 8, 100.0).unwrap();
+        let (reply, receiver) = mpsc::channel();
+
+        engine
+            .handle_command(Command::QueryChannel {
+                kind: QueryKind::DrawingExists("square".to_string()),
+                reply,
+            })
+            .unwrap();
+
+        assert_eq!(receiver.recv(), Ok(QueryResult::DrawingExists(false)));
+    }
+
+    #[test]
+    fn test_commands_interleaved_across_engines() {
+        let mut red = HeadlessEngine::new(8, 8, 100.0).unwrap();
+        let mut green = HeadlessEngine::new(8, 8, 100.0).unwrap();
+        for (engine, color) in [(&mut red, (1.0, 0.0, 0.0)), (&mut green, (0.0, 1.0, 0.0))] {
+            engine
+                .handle_command(Command::DrawDescription {
+                    name: "square".to_string(),
+                    description: DrawingDescription::plain(square(color)),
+                })
+                .unwrap();
+        }
+
+        let green_image = green.render().unwrap();
+        drop(green);
+        let red_image = red.render().unwrap();
+
+        assert_eq!(*red_image.get_pixel(4, 4), Rgba([255, 0, 0, 255]));
+        assert_eq!(*green_image.get_pixel(4, 4), Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_last_working_program() {
+        let directory = env::temp_dir().join(format!(
+            "isometric-hot-reload-{:?}",
+            ::std::thread::current().id()
+        ));
+        fs::create_dir_all(&directory).unwrap();
+        let vertex_shader = directory.join("plain.vert");
+        let fragment_shader = directory.join("plain.frag");
+        fs::write(&vertex_shader, include_str!("graphics/shaders/plain.vert")).unwrap();
+        fs::write(&fragment_shader, "not a shader").unwrap();
+        let mut engine = HeadlessEngine::new(32, 32, 100.0).unwrap();
+        engine
+            .handle_command(Command::DrawDescription {
+                name: "square".to_string(),
+                description: DrawingDescription::plain(square((1.0, 0.0, 0.0))),
+            })
+            .unwrap();
+        engine
+            .handle_command(Command::WatchShaders(directory.clone()))
+            .unwrap();
+
+        match engine.reload_shaders() {
+            Err(HeadlessError::Engine(Error::Shader(_))) => (),
+            _ => panic!("Expected program error"),
+        }
+        assert_eq!(
+            *engine.render().unwrap().get_pixel(16, 16),
+            Rgba([255, 0, 0, 255])
+        );
+
+        fs::write(
+            &fragment_shader,
+            "#version 330 core
+            out vec4 Color;
+            void main() { Color = vec4(0.0, 1.0, 0.0, 1.0); }",
+        )
+        .unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&fragment_shader)
+            .unwrap()
+            .set_modified(::std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+        engine.reload_shaders().unwrap();
+        assert_eq!(
+            *engine.render().unwrap().get_pixel(16, 16),
+            Rgba([0, 255, 0, 255])
+        );
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
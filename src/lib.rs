@@ -1,20 +1,42 @@
+mod clock;
 mod color;
 pub mod coords;
+mod custom;
 mod engine;
+mod error;
 pub mod event_handlers;
 mod events;
 mod font;
 mod graphics;
+mod headless;
+mod query;
+pub mod snapshot;
+mod software;
 pub mod terrain;
+mod timer;
 mod transform;
 mod utils;
 
+pub use clock::*;
 pub use color::Color;
+pub use custom::*;
 pub use engine::*;
+pub use error::Error;
 pub use events::*;
 pub use font::*;
+pub use graphics::culling::*;
+pub use graphics::device;
 pub use graphics::drawing;
+pub use graphics::egl::HeadlessError;
+pub use graphics::engine::{DrawOrder, DrawingType, FrameUniforms};
+pub use graphics::program::{Program, ProgramSetup};
+pub use graphics::recorder::RecordingOptions;
+pub use graphics::render_target::*;
 pub use graphics::texture::*;
+pub use headless::HeadlessEngine;
+pub use query::*;
+pub use software::SoftwareEngine;
+pub use transform::{Identity, Isometric, Projection, Transform};
 
 pub use glutin::ElementState;
 pub use glutin::MouseButton;
@@ -23,6 +45,8 @@ pub use glutin::VirtualKeyCode;
 extern crate glutin;
 pub extern crate image;
 pub extern crate nalgebra as na;
+extern crate rayon;
+extern crate shared_library;
 
 use std::fmt::Debug;
 
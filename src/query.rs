@@ -0,0 +1,195 @@
+use coords::*;
+use graphics::drawing::MeshStats;
+use graphics::engine::GraphicsEngine;
+
+pub enum QueryKind {
+    Scale,
+    Translation,
+    Rotation,
+    ViewportSize,
+    WorldCoord(GLCoord4D),
+    WorldCoordAt(glutin::dpi::PhysicalPosition),
+    DrawingExists(String),
+    MeshStats(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum QueryResult {
+    Scale(GLCoord3D),
+    Translation(GLCoord2D),
+    Rotation { yaw: f32, pitch: f32 },
+    ViewportSize(glutin::dpi::PhysicalSize),
+    WorldCoord(WorldCoord),
+    DrawingExists(bool),
+    MeshStats(Option<MeshStats>),
+}
+
+pub struct EngineView<'a> {
+    graphics: &'a GraphicsEngine,
+}
+
+impl<'a> EngineView<'a> {
+    pub fn new(graphics: &'a GraphicsEngine) -> EngineView<'a> {
+        EngineView { graphics }
+    }
+
+    pub fn get_scale(&self) -> GLCoord3D {
+        self.graphics.get_scale()
+    }
+
+    pub fn get_translation(&self) -> GLCoord2D {
+        self.graphics.get_translation()
+    }
+
+    pub fn get_yaw(&self) -> f32 {
+        self.graphics.get_projection().yaw
+    }
+
+    pub fn get_pitch(&self) -> f32 {
+        self.graphics.get_projection().pitch
+    }
+
+    pub fn get_viewport_size(&self) -> glutin::dpi::PhysicalSize {
+        self.graphics.get_viewport_size()
+    }
+
+    pub fn get_world_coord(&self, gl_coord: GLCoord4D) -> WorldCoord {
+        self.graphics.get_world_coord(gl_coord)
+    }
+
+    pub fn get_world_coord_at(&self, position: glutin::dpi::PhysicalPosition) -> WorldCoord {
+        self.graphics.get_world_coord_at(position)
+    }
+
+    pub fn has_drawing(&self, name: &str) -> bool {
+        self.graphics.has_drawing(name)
+    }
+
+    pub fn get_mesh_stats(&self, name: &str) -> Option<MeshStats> {
+        self.graphics.get_mesh_stats(name)
+    }
+
+    pub fn query(&self, kind: &QueryKind) -> QueryResult {
+        match kind {
+            QueryKind::Scale => QueryResult::Scale(self.get_scale()),
+            QueryKind::Translation => QueryResult::Translation(self.get_translation()),
+            QueryKind::Rotation => QueryResult::Rotation {
+                yaw: self.get_yaw(),
+                pitch: self.get_pitch(),
+            },
+            QueryKind::ViewportSize => QueryResult::ViewportSize(self.get_viewport_size()),
+            QueryKind::WorldCoord(gl_coord) => {
+                QueryResult::WorldCoord(self.get_world_coord(*gl_coord))
+            }
+            QueryKind::WorldCoordAt(position) => {
+                QueryResult::WorldCoord(self.get_world_coord_at(*position))
+            }
+            QueryKind::DrawingExists(name) => QueryResult::DrawingExists(self.has_drawing(name)),
+            QueryKind::MeshStats(name) => QueryResult::MeshStats(self.get_mesh_stats(name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use color::Color;
+    use graphics::device::RecordingDevice;
+    use graphics::drawing::{
+        AngleSquareColoring, DescribedDrawing, DrawingDescription, TerrainDescription,
+    };
+    use std::f32::consts::PI;
+    use terrain::Terrain;
+    use {v3, M};
+
+    fn graphics() -> GraphicsEngine {
+        RecordingDevice::install();
+        GraphicsEngine::new(0.01, glutin::dpi::PhysicalSize::new(64.0, 32.0)).unwrap()
+    }
+
+    #[test]
+    fn test_query_view_state() {
+        let graphics = graphics();
+        let view = EngineView::new(&graphics);
+
+        assert_eq!(
+            view.query(&QueryKind::Scale),
+            QueryResult::Scale(GLCoord3D::new(1.0, 2.0, 0.01))
+        );
+        assert_eq!(
+            view.query(&QueryKind::Translation),
+            QueryResult::Translation(GLCoord2D::new(0.0, 0.0))
+        );
+        assert_eq!(
+            view.query(&QueryKind::Rotation),
+            QueryResult::Rotation {
+                yaw: PI / 4.0,
+                pitch: PI / 3.0
+            }
+        );
+        assert_eq!(
+            view.query(&QueryKind::ViewportSize),
+            QueryResult::ViewportSize(glutin::dpi::PhysicalSize::new(64.0, 32.0))
+        );
+    }
+
+    #[test]
+    fn test_query_world_coord_round_trips_projection() {
+        let mut graphics = graphics();
+        let world_coord = WorldCoord::new(1.0, 2.0, 3.0);
+        let gl_coord = world_coord.to_gl_coord_4d(graphics.get_transform());
+
+        match EngineView::new(&graphics).query(&QueryKind::WorldCoord(gl_coord)) {
+            QueryResult::WorldCoord(actual) => assert!(
+                (actual.x - world_coord.x).abs() < 1e-4
+                    && (actual.y - world_coord.y).abs() < 1e-4
+                    && (actual.z - world_coord.z).abs() < 1e-4
+            ),
+            _ => panic!("Expected world coord"),
+        }
+    }
+
+    #[test]
+    fn test_query_drawing_exists() {
+        let mut graphics = graphics();
+        graphics.add_drawing(
+            "square".to_string(),
+            Box::new(DescribedDrawing::new(
+                DrawingDescription::plain(vec![]),
+                None,
+            )),
+        );
+        let view = EngineView::new(&graphics);
+
+        assert_eq!(
+            view.query(&QueryKind::DrawingExists("square".to_string())),
+            QueryResult::DrawingExists(true)
+        );
+        assert!(!view.has_drawing("circle"));
+    }
+
+    #[test]
+    fn test_query_mesh_stats() {
+        let mut graphics = graphics();
+        let terrain = Terrain::new(M::zeros(5, 5), &vec![], &vec![]);
+        let color_matrix = M::from_element(4, 4, Color::new(1.0, 0.0, 0.0, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+        let description =
+            TerrainDescription::describe(&terrain, &color_matrix, &shading, 2, &|_, _| ())
+                .with_unindexed_upload_timing();
+        graphics
+            .add_terrain("terrain".to_string(), description)
+            .unwrap();
+        let view = EngineView::new(&graphics);
+
+        match view.query(&QueryKind::MeshStats("terrain".to_string())) {
+            QueryResult::MeshStats(Some(stats)) => {
+                assert!(stats.saved_bytes() > 0);
+                assert!(stats.unindexed_upload_time.is_some());
+            }
+            _ => panic!("Expected mesh stats"),
+        }
+        assert_eq!(view.get_mesh_stats("missing"), None);
+    }
+}
@@ -0,0 +1,311 @@
+use image::{Rgba, RgbaImage};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    MissingReference(PathBuf),
+    DimensionsDiffer {
+        actual: (u32, u32),
+        expected: (u32, u32),
+    },
+    PixelsDiffer {
+        mismatched_pixels: usize,
+        max_difference: u8,
+        diff: PathBuf,
+        actual: PathBuf,
+    },
+    Io(String),
+}
+
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub max_difference: u8,
+    pub diff: RgbaImage,
+}
+
+impl Comparison {
+    pub fn matches(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+pub fn compare_images(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Comparison {
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+        let a = actual.get_pixel(x, y);
+        let e = expected.get_pixel(x, y);
+        let difference = (0..4)
+            .map(|c| (a[c] as i16 - e[c] as i16).unsigned_abs() as u8)
+            .max()
+            .unwrap();
+        max_difference = max_difference.max(difference);
+        if difference > tolerance {
+            mismatched_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            Rgba([a[0] / 4, a[1] / 4, a[2] / 4, 255])
+        }
+    });
+    Comparison {
+        mismatched_pixels,
+        max_difference,
+        diff,
+    }
+}
+
+pub struct Snapshot {
+    reference_directory: PathBuf,
+    output_directory: PathBuf,
+    tolerance: u8,
+}
+
+impl Snapshot {
+    pub const BLESS_VARIABLE: &'static str = "ISOMETRIC_BLESS_SNAPSHOTS";
+
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(
+        reference_directory: P,
+        output_directory: Q,
+    ) -> Snapshot {
+        Snapshot {
+            reference_directory: reference_directory.as_ref().to_path_buf(),
+            output_directory: output_directory.as_ref().to_path_buf(),
+            tolerance: 0,
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: u8) -> Snapshot {
+        self.tolerance = tolerance;
+        self
+    }
+
+    fn blessing() -> bool {
+        env::var(Snapshot::BLESS_VARIABLE)
+            .map(|value| !value.is_empty() && value != "0")
+            .unwrap_or(false)
+    }
+
+    fn reference_path(&self, name: &str) -> PathBuf {
+        self.reference_directory.join(format!("{}.png", name))
+    }
+
+    fn save(image: &RgbaImage, path: &Path) -> Result<(), SnapshotError> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory).map_err(|err| SnapshotError::Io(err.to_string()))?;
+        }
+        image
+            .save(path)
+            .map_err(|err| SnapshotError::Io(err.to_string()))
+    }
+
+    pub fn check(&self, name: &str, actual: &RgbaImage) -> Result<(), SnapshotError> {
+        let reference_path = self.reference_path(name);
+        if Snapshot::blessing() {
+            return Snapshot::save(actual, &reference_path);
+        }
+
+        let expected = match image::open(&reference_path) {
+            Ok(expected) => expected.to_rgba(),
+            Err(_) => return Err(SnapshotError::MissingReference(reference_path)),
+        };
+        if actual.dimensions() != expected.dimensions() {
+            return Err(SnapshotError::DimensionsDiffer {
+                actual: actual.dimensions(),
+                expected: expected.dimensions(),
+            });
+        }
+
+        let comparison = compare_images(actual, &expected, self.tolerance);
+        if comparison.matches() {
+            return Ok(());
+        }
+        let diff = self.output_directory.join(format!("{}.diff.png", name));
+        let actual_path = self.output_directory.join(format!("{}.actual.png", name));
+        Snapshot::save(&comparison.diff, &diff)?;
+        Snapshot::save(actual, &actual_path)?;
+        Err(SnapshotError::PixelsDiffer {
+            mismatched_pixels: comparison.mismatched_pixels,
+            max_difference: comparison.max_difference,
+            diff,
+            actual: actual_path,
+        })
+    }
+
+    pub fn assert(&self, name: &str, actual: &RgbaImage) {
+        if let Err(err) = self.check(name, actual) {
+            panic!(
+                "Snapshot {} does not match reference: {:?} (set {}=1 to bless)",
+                name,
+                err,
+                Snapshot::BLESS_VARIABLE
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use color::Color;
+    use coords::*;
+    use engine::Command;
+    use font::Font;
+    use graphics::drawing::*;
+    use headless::HeadlessEngine;
+    use std::f32::consts::PI;
+    use std::sync::Arc;
+    use terrain::Terrain;
+    use transform::{Isometric, Transform};
+    use {v2, v3, M};
+
+    fn snapshot() -> Snapshot {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        Snapshot::new(root.join("snapshots"), root.join("target/snapshots")).with_tolerance(2)
+    }
+
+    fn engine() -> HeadlessEngine {
+        let mut engine = HeadlessEngine::new(128, 128, 16.0).unwrap();
+        engine.set_transform(Transform::new(
+            GLCoord3D::new(0.15, 0.15, 1.0 / 16.0),
+            GLCoord2D::new(0.0, 0.0),
+            Box::new(Isometric::new(PI / 4.0, PI / 3.0)),
+        ));
+        engine
+    }
+
+    fn draw(engine: &mut HeadlessEngine, name: &str, drawing: Box<Drawing>) {
+        engine.add_drawing(name.to_string(), drawing);
+    }
+
+    #[test]
+    fn test_compare_identical_images() {
+        let image = RgbaImage::from_pixel(2, 2, Rgba([10, 20, 30, 255]));
+
+        let comparison = compare_images(&image, &image, 0);
+
+        assert!(comparison.matches());
+        assert_eq!(comparison.max_difference, 0);
+    }
+
+    #[test]
+    fn test_compare_within_tolerance() {
+        let actual = RgbaImage::from_pixel(2, 2, Rgba([10, 20, 30, 255]));
+        let expected = RgbaImage::from_pixel(2, 2, Rgba([12, 20, 29, 255]));
+
+        let comparison = compare_images(&actual, &expected, 2);
+
+        assert!(comparison.matches());
+        assert_eq!(comparison.max_difference, 2);
+    }
+
+    #[test]
+    fn test_compare_marks_mismatched_pixels_in_diff() {
+        let actual = RgbaImage::from_pixel(2, 1, Rgba([0, 0, 0, 255]));
+        let mut expected = actual.clone();
+        expected.put_pixel(1, 0, Rgba([0, 100, 0, 255]));
+
+        let comparison = compare_images(&actual, &expected, 2);
+
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.max_difference, 100);
+        assert_eq!(*comparison.diff.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+        assert_eq!(*comparison.diff.get_pixel(1, 0), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_terrain_snapshot() {
+        let mut engine = engine();
+        let heights = M::from_fn(9, 9, |x, y| {
+            2.0 - ((x as f32 - 4.0).abs() + (y as f32 - 4.0).abs()) / 4.0
+        });
+        let terrain = Terrain::new(heights, &vec![], &vec![]);
+        let color_matrix = M::from_element(8, 8, Color::new(0.2, 0.8, 0.2, 1.0));
+        let shading: Box<SquareColoring> = Box::new(AngleSquareColoring::new(
+            Color::new(1.0, 1.0, 1.0, 1.0),
+            v3(1.0, 0.0, 1.0),
+        ));
+        let mut drawing = TerrainDrawing::new(8, 8, 8);
+        drawing
+            .update(&terrain, &color_matrix, &shading, v2(0, 0), v2(8, 8))
+            .unwrap();
+        engine
+            .handle_command(Command::LookAt(WorldCoord::new(4.0, 4.0, 0.0)))
+            .unwrap();
+        draw(&mut engine, "terrain", Box::new(drawing));
+
+        snapshot().assert("terrain", &engine.render().unwrap());
+    }
+
+    #[test]
+    fn test_terrain_description_matches_terrain_snapshot() {
+        let mut engine = engine();
+        let heights = M::from_fn(9, 9, |x, y| {
+            2.0 - ((x as f32 - 4.0).abs() + (y as f32 - 4.0).abs()) / 4.0
+        });
+        let terrain = Terrain::new(heights, &vec![], &vec![]);
+        let color_matrix = M::from_element(8, 8, Color::new(0.2, 0.8, 0.2, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+        let description =
+            TerrainDescription::describe(&terrain, &color_matrix, &shading, 8, &|_, _| ());
+        engine
+            .handle_command(Command::LookAt(WorldCoord::new(4.0, 4.0, 0.0)))
+            .unwrap();
+        engine
+            .handle_command(Command::DrawTerrain {
+                name: "terrain".to_string(),
+                description,
+            })
+            .unwrap();
+
+        snapshot().assert("terrain", &engine.render().unwrap());
+    }
+
+    #[test]
+    fn test_house_snapshot() {
+        let mut engine = engine();
+        let house = HouseDrawing::new(
+            WorldCoord::new(0.0, 0.0, 0.0),
+            1.0,
+            1.0,
+            0.5,
+            Color::new(1.0, 0.0, 0.0, 1.0),
+            v3(1.0, 0.0, 1.0),
+        );
+        draw(&mut engine, "house", Box::new(house));
+
+        snapshot().assert("house", &engine.render().unwrap());
+    }
+
+    #[test]
+    fn test_text_snapshot() {
+        let mut engine = engine();
+        let csv = env::temp_dir().join(format!(
+            "isometric-snapshot-font-{:?}.csv",
+            ::std::thread::current().id()
+        ));
+        fs::write(&csv, "65,0,0,8,8,0,0,9\n63,0,0,8,8,0,0,9").unwrap();
+        let glyph = RgbaImage::from_fn(8, 8, |x, y| {
+            if x == 0 || y == 0 || x == y {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+        let font = Font::from_csv_and_image(csv.to_str().unwrap(), glyph).unwrap();
+        fs::remove_file(&csv).unwrap();
+        draw(
+            &mut engine,
+            "text",
+            Box::new(Text::new(
+                "AA",
+                WorldCoord::new(0.0, 0.0, 0.0),
+                Arc::new(font),
+            )),
+        );
+
+        snapshot().assert("text", &engine.render().unwrap());
+    }
+}
@@ -0,0 +1,337 @@
+use color::Color;
+use coords::*;
+use engine::Command;
+use graphics::drawing::DrawingDescription;
+use graphics::engine::{DrawOrder, DrawingType, GraphicsEngine};
+use graphics::rasterizer::{Rasterizer, Uniforms};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use transform::{Isometric, Transform};
+
+pub struct SoftwareEngine {
+    rasterizer: Rasterizer,
+    viewport_size: glutin::dpi::PhysicalSize,
+    transform: Transform,
+    projection: Isometric,
+    drawings: HashMap<String, DrawingDescription>,
+    draw_order: DrawOrder,
+    clear_color: [f32; 4],
+}
+
+impl SoftwareEngine {
+    pub fn new(width: u32, height: u32, max_z: f32) -> SoftwareEngine {
+        let projection = Isometric::new(PI / 4.0, PI / 3.0);
+        let transform = Transform::new(
+            GLCoord3D::new(1.0, width as f32 / height as f32, 1.0 / max_z),
+            GLCoord2D::new(0.0, 0.0),
+            Box::new(projection),
+        );
+
+        SoftwareEngine {
+            rasterizer: Rasterizer::new(width, height),
+            viewport_size: glutin::dpi::PhysicalSize::new(width as f64, height as f64),
+            transform,
+            projection,
+            drawings: HashMap::new(),
+            draw_order: DrawOrder::default(),
+            clear_color: GraphicsEngine::CLEAR_COLOR,
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    pub fn set_clear_color(&mut self, color: Color) {
+        self.clear_color = [color.r, color.g, color.b, color.a];
+    }
+
+    pub fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Resize(physical_size) => self.set_viewport_size(physical_size),
+            Command::Translate(translation) => self.transform.translate(translation),
+            Command::Scale { center, scale } => self.transform.scale(center, scale),
+            Command::Rotate { center, yaw } => self.rotate(center, yaw),
+            Command::DrawDescription { name, description } => {
+                self.drawings.insert(name, description);
+            }
+            Command::DrawTerrain { name, description } => {
+                self.drawings
+                    .insert(name, DrawingDescription::plain(description.vertices()));
+            }
+            Command::Erase(name) => {
+                self.drawings.remove(&name);
+            }
+            Command::LookAt(world_coord) => self.transform.look_at(world_coord),
+            Command::SetDrawOrder(draw_order) => self.draw_order = draw_order,
+            _ => (),
+        }
+    }
+
+    fn set_viewport_size(&mut self, viewport_size: glutin::dpi::PhysicalSize) {
+        self.transform.scale(
+            GLCoord4D::new(0.0, 0.0, 0.0, 1.0),
+            GLCoord2D::new(
+                (self.viewport_size.width as f32) / (viewport_size.width as f32),
+                (self.viewport_size.height as f32) / (viewport_size.height as f32),
+            ),
+        );
+        self.viewport_size = viewport_size;
+        self.rasterizer = Rasterizer::new(viewport_size.width as u32, viewport_size.height as u32);
+    }
+
+    fn rotate(&mut self, center: GLCoord4D, yaw: f32) {
+        self.projection.yaw = (self.projection.yaw + PI * 2.0 + yaw) % (PI * 2.0);
+        let projection = self.projection;
+        self.transform.transform_maintaining_center(
+            center,
+            Box::new(move |transform| transform.set_projection(Box::new(projection))),
+        );
+    }
+
+    pub fn render(&mut self) -> Result<RgbaImage, String> {
+        self.rasterizer.clear(self.clear_color);
+        let draw_order = self.draw_order.clone();
+        for drawing_type in draw_order.world.into_iter().chain(draw_order.ui) {
+            self.draw(drawing_type)?;
+        }
+        Ok(self.rasterizer.image())
+    }
+
+    fn uniforms(&self, description: &DrawingDescription) -> Uniforms {
+        Uniforms {
+            projection: self.transform.compute_transformation_matrix(),
+            z_mod: match description.drawing_type {
+                DrawingType::Plain => description.z_mod,
+                _ => 0.0,
+            },
+            pixel_to_screen: na::Matrix2::new(
+                2.0 / self.viewport_size.width as f32,
+                0.0,
+                0.0,
+                2.0 / self.viewport_size.height as f32,
+            ),
+            world_to_screen: self.transform.get_scale_as_matrix(),
+        }
+    }
+
+    fn draw(&mut self, drawing_type: DrawingType) -> Result<(), String> {
+        let visible: Vec<&DrawingDescription> = self
+            .drawings
+            .values()
+            .filter(|description| description.drawing_type == drawing_type)
+            .filter(|description| match description.visibility_check_coord {
+                Some(world_coord) => self.is_visible(&world_coord),
+                None => true,
+            })
+            .collect();
+        for description in visible {
+            let uniforms = self.uniforms(description);
+            let texture = description
+                .texture
+                .as_ref()
+                .map(|texture| texture.image().as_ref());
+            self.rasterizer
+                .draw(drawing_type, &description.vertices, &uniforms, texture)?;
+        }
+        Ok(())
+    }
+
+    fn is_visible(&self, world_coord: &WorldCoord) -> bool {
+        let gl_coord_4 = world_coord.to_gl_coord_4d(&self.transform);
+        let gl_coord_2 = GLCoord2D::new(gl_coord_4.x, gl_coord_4.y);
+        let buffer_coord = gl_coord_2.to_buffer_coord(self.viewport_size);
+        let actual_z = self.rasterizer.get_z_at(buffer_coord);
+
+        gl_coord_4.z - actual_z <= 0.01
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use color::Color;
+    use font::Font;
+    use graphics::device::RecordingDevice;
+    use graphics::drawing::*;
+    use headless::HeadlessEngine;
+    use image::Rgba;
+    use snapshot::compare_images;
+    use std::env;
+    use std::fs;
+    use std::sync::Arc;
+    use terrain::Terrain;
+    use {v3, M};
+
+    fn house() -> DrawingDescription {
+        HouseDrawing::describe(
+            WorldCoord::new(0.0, 0.0, 0.0),
+            0.5,
+            0.5,
+            0.25,
+            Color::new(1.0, 0.0, 0.0, 1.0),
+            v3(1.0, 0.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn test_render_empty_frame_is_clear_color() {
+        let mut engine = SoftwareEngine::new(8, 4, 100.0);
+
+        let image = engine.render().unwrap();
+
+        assert_eq!(image.dimensions(), (8, 4));
+        assert!(image.pixels().all(|pixel| *pixel == Rgba([0, 0, 255, 255])));
+    }
+
+    #[test]
+    fn test_render_uses_clear_color() {
+        let mut engine = SoftwareEngine::new(4, 4, 100.0);
+        engine.set_clear_color(Color::new(1.0, 0.0, 0.0, 1.0));
+
+        assert!(engine
+            .render()
+            .unwrap()
+            .pixels()
+            .all(|pixel| *pixel == Rgba([255, 0, 0, 255])));
+    }
+
+    #[test]
+    fn test_render_follows_draw_order() {
+        let mut engine = SoftwareEngine::new(16, 16, 100.0);
+        engine.handle_command(Command::DrawDescription {
+            name: "house".to_string(),
+            description: house(),
+        });
+        engine.handle_command(Command::SetDrawOrder(DrawOrder {
+            world: vec![],
+            ui: vec![DrawingType::Text],
+        }));
+
+        assert!(engine
+            .render()
+            .unwrap()
+            .pixels()
+            .all(|pixel| *pixel == Rgba([0, 0, 255, 255])));
+    }
+
+    #[test]
+    fn test_render_custom_drawing_is_error() {
+        const CUSTOM: DrawingType = DrawingType::Custom {
+            name: "custom",
+            attributes: &[3],
+        };
+        let mut engine = SoftwareEngine::new(4, 4, 100.0);
+        engine.handle_command(Command::DrawDescription {
+            name: "custom".to_string(),
+            description: DrawingDescription {
+                drawing_type: CUSTOM,
+                vertices: vec![0.0; 9],
+                texture: None,
+                z_mod: 0.0,
+                visibility_check_coord: None,
+                uniforms: vec![],
+            },
+        });
+        engine.handle_command(Command::SetDrawOrder(DrawOrder {
+            world: vec![CUSTOM],
+            ui: vec![],
+        }));
+
+        assert_eq!(
+            engine.render().err(),
+            Some("Cannot rasterize custom drawing type custom".to_string())
+        );
+    }
+
+    #[test]
+    fn test_erase() {
+        let mut engine = SoftwareEngine::new(16, 16, 100.0);
+        engine.handle_command(Command::DrawDescription {
+            name: "house".to_string(),
+            description: house(),
+        });
+        engine.handle_command(Command::Erase("house".to_string()));
+
+        assert!(engine
+            .render()
+            .unwrap()
+            .pixels()
+            .all(|pixel| *pixel == Rgba([0, 0, 255, 255])));
+    }
+
+    fn font() -> Font {
+        let csv = env::temp_dir().join(format!(
+            "isometric-software-font-{:?}.csv",
+            ::std::thread::current().id()
+        ));
+        fs::write(&csv, "65,0,0,8,8,0,0,9\n63,0,0,8,8,0,0,9").unwrap();
+        let glyph = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 255]));
+        let font = Font::from_csv_and_image(csv.to_str().unwrap(), glyph).unwrap();
+        fs::remove_file(&csv).unwrap();
+        font
+    }
+
+    #[test]
+    fn test_render_terrain_and_text_without_gl() {
+        let device = RecordingDevice::install();
+        let mut engine = SoftwareEngine::new(64, 64, 16.0);
+        engine.set_transform(Transform::new(
+            GLCoord3D::new(0.15, 0.15, 1.0 / 16.0),
+            GLCoord2D::new(0.0, 0.0),
+            Box::new(Isometric::new(PI / 4.0, PI / 3.0)),
+        ));
+        let terrain = Terrain::new(M::from_element(9, 9, 0.0), &vec![], &vec![]);
+        let color_matrix = M::from_element(8, 8, Color::new(0.0, 1.0, 0.0, 1.0));
+        let shading = AngleSquareColoring::new(Color::new(1.0, 1.0, 1.0, 1.0), v3(1.0, 0.0, 1.0));
+        engine.handle_command(Command::LookAt(WorldCoord::new(4.0, 4.0, 0.0)));
+        engine.handle_command(Command::DrawTerrain {
+            name: "terrain".to_string(),
+            description: TerrainDescription::describe(
+                &terrain,
+                &color_matrix,
+                &shading,
+                4,
+                &|_, _| (),
+            ),
+        });
+        engine.handle_command(Command::DrawDescription {
+            name: "text".to_string(),
+            description: Text::describe("A", WorldCoord::new(4.0, 4.0, 0.0), Arc::new(font())),
+        });
+
+        let image = engine.render().unwrap();
+
+        assert!(device.calls().is_empty());
+        assert_eq!(*image.get_pixel(32, 28), Rgba([255, 255, 255, 255]));
+        assert!(image
+            .pixels()
+            .any(|pixel| pixel[1] > 0 && pixel[0] == 0 && pixel[2] == 0));
+    }
+
+    #[test]
+    fn test_matches_gl_output() {
+        let mut software = SoftwareEngine::new(64, 48, 100.0);
+        let mut headless = HeadlessEngine::new(64, 48, 100.0).unwrap();
+        let rotate = || Command::Rotate {
+            center: GLCoord4D::new(0.0, 0.0, 0.0, 1.0),
+            yaw: PI / 8.0,
+        };
+        let draw = || Command::DrawDescription {
+            name: "house".to_string(),
+            description: house(),
+        };
+        software.handle_command(draw());
+        software.handle_command(rotate());
+        headless.handle_command(draw()).unwrap();
+        headless.handle_command(rotate()).unwrap();
+
+        let actual = software.render().unwrap();
+        let comparison = compare_images(&actual, &headless.render().unwrap(), 2);
+
+        assert!(actual.pixels().any(|pixel| pixel[0] > 0));
+        assert!(comparison.mismatched_pixels <= 64 * 48 / 100);
+    }
+}
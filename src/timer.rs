@@ -0,0 +1,296 @@
+use engine::{Command, Event};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+enum Action {
+    Command(Command),
+    Fire { repeat: Option<Duration> },
+}
+
+struct Timer {
+    id: String,
+    due: Duration,
+    action: Action,
+}
+
+#[derive(Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    pub fn new() -> Timers {
+        Timers::default()
+    }
+
+    pub fn schedule(&mut self, now: Duration, id: String, delay: Duration, command: Command) {
+        self.cancel(&id);
+        self.timers.push(Timer {
+            id,
+            due: now + delay,
+            action: Action::Command(command),
+        });
+    }
+
+    pub fn start(
+        &mut self,
+        now: Duration,
+        id: String,
+        interval: Duration,
+        repeat: bool,
+    ) -> Result<(), String> {
+        if interval == Duration::default() {
+            return Err(format!("Timer {} has a zero interval", id));
+        }
+        self.cancel(&id);
+        self.timers.push(Timer {
+            id,
+            due: now + interval,
+            action: Action::Fire {
+                repeat: if repeat { Some(interval) } else { None },
+            },
+        });
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, id: &str) {
+        self.timers.retain(|timer| timer.id != id);
+    }
+
+    pub fn next_due(&self) -> Option<Duration> {
+        self.timers.iter().map(|timer| timer.due).min()
+    }
+
+    pub fn due(&mut self, now: Duration) -> Vec<Command> {
+        let (mut due, pending): (Vec<Timer>, Vec<Timer>) =
+            self.timers.drain(..).partition(|timer| timer.due <= now);
+        self.timers = pending;
+        due.sort_by_key(|timer| timer.due);
+
+        let mut out = vec![];
+        for timer in due {
+            match timer.action {
+                Action::Command(command) => out.push(command),
+                Action::Fire { repeat } => {
+                    out.push(Command::Event(Event::TimerFired(timer.id.clone())));
+                    if let Some(interval) = repeat {
+                        let mut next = timer.due + interval;
+                        if next <= now {
+                            next = now + interval;
+                        }
+                        self.timers.push(Timer {
+                            id: timer.id,
+                            due: next,
+                            action: Action::Fire { repeat },
+                        });
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Default)]
+struct Deadline {
+    at: Option<Instant>,
+    closed: bool,
+}
+
+pub struct TimerThread {
+    deadline: Arc<(Mutex<Deadline>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TimerThread {
+    pub fn new(wake: Box<Fn() + Send>) -> TimerThread {
+        let deadline = Arc::new((Mutex::new(Deadline::default()), Condvar::new()));
+        let worker_deadline = deadline.clone();
+        let thread = thread::spawn(move || TimerThread::work(&worker_deadline, wake));
+        TimerThread {
+            deadline,
+            thread: Some(thread),
+        }
+    }
+
+    fn work(deadline: &(Mutex<Deadline>, Condvar), wake: Box<Fn() + Send>) {
+        let (lock, condvar) = deadline;
+        let mut deadline = lock.lock().unwrap();
+        while !deadline.closed {
+            match deadline.at {
+                None => deadline = condvar.wait(deadline).unwrap(),
+                Some(at) => {
+                    let now = Instant::now();
+                    if now >= at {
+                        deadline.at = None;
+                        drop(deadline);
+                        wake();
+                        deadline = lock.lock().unwrap();
+                    } else {
+                        deadline = condvar.wait_timeout(deadline, at - now).unwrap().0;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn wake_at(&self, at: Option<Instant>) {
+        let (lock, condvar) = &*self.deadline;
+        lock.lock().unwrap().at = at;
+        condvar.notify_all();
+    }
+}
+
+impl Drop for TimerThread {
+    fn drop(&mut self) {
+        {
+            let (lock, condvar) = &*self.deadline;
+            lock.lock().unwrap().closed = true;
+            condvar.notify_all();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn millis(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn fired(commands: Vec<Command>) -> Vec<String> {
+        commands
+            .into_iter()
+            .map(|command| match command {
+                Command::Event(Event::TimerFired(id)) => id,
+                Command::Erase(name) => name,
+                _ => panic!("Unexpected command"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scheduled_command_is_returned_once_due() {
+        let mut timers = Timers::new();
+        timers.schedule(
+            millis(100),
+            "tooltip".to_string(),
+            millis(50),
+            Command::Erase("tooltip".to_string()),
+        );
+
+        assert_eq!(fired(timers.due(millis(149))), Vec::<String>::new());
+        assert_eq!(timers.next_due(), Some(millis(150)));
+        assert_eq!(fired(timers.due(millis(150))), vec!["tooltip"]);
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn test_repeating_timer_fires_until_cancelled() {
+        let mut timers = Timers::new();
+        timers
+            .start(millis(0), "label".to_string(), millis(1000), true)
+            .unwrap();
+
+        assert_eq!(fired(timers.due(millis(1000))), vec!["label"]);
+        assert_eq!(fired(timers.due(millis(1500))), Vec::<String>::new());
+        assert_eq!(fired(timers.due(millis(2000))), vec!["label"]);
+        assert_eq!(fired(timers.due(millis(5500))), vec!["label"]);
+        assert_eq!(timers.next_due(), Some(millis(6500)));
+
+        timers.cancel("label");
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn test_one_shot_timer_fires_once() {
+        let mut timers = Timers::new();
+        timers
+            .start(millis(0), "retry".to_string(), millis(10), false)
+            .unwrap();
+
+        assert_eq!(fired(timers.due(millis(20))), vec!["retry"]);
+        assert_eq!(fired(timers.due(millis(40))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_starting_timer_replaces_timer_with_same_id() {
+        let mut timers = Timers::new();
+        timers
+            .start(millis(0), "retry".to_string(), millis(10), false)
+            .unwrap();
+        timers
+            .start(millis(0), "retry".to_string(), millis(30), false)
+            .unwrap();
+
+        assert_eq!(timers.next_due(), Some(millis(30)));
+    }
+
+    #[test]
+    fn test_due_timers_are_returned_in_order() {
+        let mut timers = Timers::new();
+        timers.schedule(
+            millis(0),
+            "second".to_string(),
+            millis(20),
+            Command::Erase("second".to_string()),
+        );
+        timers.schedule(
+            millis(0),
+            "first".to_string(),
+            millis(10),
+            Command::Erase("first".to_string()),
+        );
+
+        assert_eq!(fired(timers.due(millis(30))), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn test_scheduled_command_can_be_cancelled() {
+        let mut timers = Timers::new();
+        timers.schedule(
+            millis(0),
+            "hide tooltip".to_string(),
+            millis(2000),
+            Command::Erase("tooltip".to_string()),
+        );
+
+        timers.cancel("hide tooltip");
+
+        assert_eq!(timers.next_due(), None);
+        assert_eq!(fired(timers.due(millis(2000))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_zero_interval_is_rejected() {
+        let mut timers = Timers::new();
+
+        assert!(timers
+            .start(millis(0), "spin".to_string(), millis(0), true)
+            .is_err());
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn test_timer_thread_wakes_at_latest_deadline() {
+        let (tx, rx) = ::std::sync::mpsc::channel();
+        let timer_thread = TimerThread::new(Box::new(move || {
+            let _ = tx.send(Instant::now());
+        }));
+        let start = Instant::now();
+
+        timer_thread.wake_at(Some(start + millis(1000)));
+        timer_thread.wake_at(Some(start + millis(20)));
+        let woken = rx.recv_timeout(millis(500)).unwrap();
+
+        assert!(woken >= start + millis(20));
+        timer_thread.wake_at(None);
+        assert!(rx.recv_timeout(millis(50)).is_err());
+    }
+}
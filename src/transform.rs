@@ -1,6 +1,6 @@
 use super::coords::*;
 
-pub trait Projection {
+pub trait Projection: Send {
     fn compute_projection_matrix(&self) -> na::Matrix4<f32>;
 }
 
@@ -34,6 +34,7 @@ impl Projection for Isometric {
 }
 
 #[allow(dead_code)]
+#[derive(Default)]
 pub struct Identity {}
 
 #[allow(dead_code)]
@@ -80,6 +81,14 @@ impl Transform {
         scale_matrix * self.projection.compute_projection_matrix()
     }
 
+    pub fn get_scale(&self) -> GLCoord3D {
+        GLCoord3D::new(self.scale.x, self.scale.y, self.scale.z)
+    }
+
+    pub fn get_translation(&self) -> GLCoord2D {
+        GLCoord2D::new(self.translation.x, self.translation.y)
+    }
+
     pub fn compute_inverse_matrix(&self) -> na::Matrix4<f32> {
         self.compute_transformation_matrix().try_inverse().unwrap()
     }
@@ -312,5 +321,4 @@ mod tests {
         assert!(gl_coord_4.x == 0.0);
         assert!(gl_coord_4.y == 0.0);
     }
-
 }
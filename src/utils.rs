@@ -43,6 +43,14 @@ impl Index2D {
     pub fn indices(&self) -> usize {
         self.columns * self.rows
     }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
 }
 
 #[cfg(test)]